@@ -0,0 +1,22 @@
+// Hash functions shared by txids, block hashes, merkle roots and checksums.
+
+use sha2::{Digest, Sha256};
+
+/// Single SHA-256.
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+/// SHA-256 applied twice, as used for txids, block hashes and base58check.
+pub fn sha256d(data: &[u8]) -> [u8; 32] {
+    sha256(&sha256(data))
+}
+
+// sha256d of the concatenation of `parts`, without building it.
+pub(crate) fn sha256d_concat(parts: &[&[u8]]) -> [u8; 32] {
+    let mut engine = Sha256::new();
+    for part in parts {
+        engine.update(part);
+    }
+    sha256(&engine.finalize())
+}
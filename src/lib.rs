@@ -1,54 +1,341 @@
+#![no_std]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod hashes;
+pub mod opcodes;
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::Infallible;
+use core::fmt;
+use core::ops::{Deref, Range};
+use core::str::FromStr;
+use hashes::{sha256d, sha256d_concat};
+use opcodes::*;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use std::fmt;
-use std::ops::Deref;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+/// Bitcoin's variable-length integer. `to_bytes` gives the 1/3/5/9-byte wire
+/// encoding; with serde it is just the number, e.g. `253` in JSON.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct CompactSize {
     pub value: u64,
 }
 
+/// Non-exhaustive because `Io` only exists with the `std` feature.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
 pub enum BitcoinError {
     InsufficientBytes,
     InvalidFormat,
+    /// A failure while parsing a larger structure, with the absolute byte
+    /// offset and name of the field that could not be decoded.
+    ParseError {
+        offset: usize,
+        field: &'static str,
+        kind: Box<BitcoinError>,
+    },
+    #[cfg(feature = "std")]
+    Io(io::ErrorKind),
+}
+
+impl BitcoinError {
+    /// The underlying error, looking through any `ParseError` position.
+    pub fn kind(&self) -> &BitcoinError {
+        match self {
+            BitcoinError::ParseError { kind, .. } => kind.kind(),
+            other => other,
+        }
+    }
+
+    // Attaches a position. Errors already positioned by a nested parser keep
+    // their more specific field and have their offset rebased.
+    fn at(self, offset: usize, field: &'static str) -> Self {
+        match self {
+            BitcoinError::ParseError {
+                offset: inner,
+                field,
+                kind,
+            } => BitcoinError::ParseError {
+                offset: offset + inner,
+                field,
+                kind,
+            },
+            kind => BitcoinError::ParseError {
+                offset,
+                field,
+                kind: Box::new(kind),
+            },
+        }
+    }
+}
+
+impl fmt::Display for BitcoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitcoinError::InsufficientBytes => write!(f, "insufficient bytes"),
+            BitcoinError::InvalidFormat => write!(f, "invalid format"),
+            BitcoinError::ParseError {
+                offset,
+                field,
+                kind,
+            } => write!(f, "{} at byte {} ({})", kind, offset, field),
+            #[cfg(feature = "std")]
+            BitcoinError::Io(kind) => write!(f, "i/o error: {}", kind),
+        }
+    }
+}
+
+impl core::error::Error for BitcoinError {}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for BitcoinError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => BitcoinError::InsufficientBytes,
+            kind => BitcoinError::Io(kind),
+        }
+    }
+}
+
+// Byte sink behind every encoder: a `Vec` for `to_bytes`, a counter for size
+// computations, and with `std` any `io::Write` for `write_to`.
+trait Encoder {
+    type Error;
+    fn emit(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+}
+
+impl Encoder for Vec<u8> {
+    type Error = Infallible;
+    fn emit(&mut self, bytes: &[u8]) -> Result<(), Infallible> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+struct SizeCounter(usize);
+
+impl Encoder for SizeCounter {
+    type Error = Infallible;
+    fn emit(&mut self, bytes: &[u8]) -> Result<(), Infallible> {
+        self.0 += bytes.len();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+struct IoEncoder<'a, W: Write>(&'a mut W);
+
+#[cfg(feature = "std")]
+impl<W: Write> Encoder for IoEncoder<'_, W> {
+    type Error = io::Error;
+    fn emit(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.0.write_all(bytes)
+    }
+}
+
+fn encode_to_vec<F>(encode: F) -> Vec<u8>
+where
+    F: FnOnce(&mut Vec<u8>) -> Result<usize, Infallible>,
+{
+    let mut bytes = Vec::new();
+    append_to_vec(&mut bytes, encode);
+    bytes
+}
+
+fn append_to_vec<F>(buf: &mut Vec<u8>, encode: F) -> usize
+where
+    F: FnOnce(&mut Vec<u8>) -> Result<usize, Infallible>,
+{
+    let Ok(written) = encode(buf);
+    written
+}
+
+fn encoded_size<F>(encode: F) -> usize
+where
+    F: FnOnce(&mut SizeCounter) -> Result<usize, Infallible>,
+{
+    let Ok(size) = encode(&mut SizeCounter(0));
+    size
+}
+
+#[cfg(feature = "std")]
+fn read_array<R: Read, const N: usize>(r: &mut R) -> Result<[u8; N], BitcoinError> {
+    let mut buf = [0u8; N];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+// Reads exactly `len` bytes without trusting `len` for the initial allocation.
+#[cfg(feature = "std")]
+fn read_vec<R: Read>(r: &mut R, len: u64) -> Result<Vec<u8>, BitcoinError> {
+    let mut bytes = Vec::new();
+    r.take(len).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < len {
+        return Err(BitcoinError::InsufficientBytes);
+    }
+    Ok(bytes)
+}
+
+// Checked slicing for the parsers: running off the end of the input is
+// `InsufficientBytes`, never a panic.
+fn slice_from(bytes: &[u8], offset: usize) -> Result<&[u8], BitcoinError> {
+    bytes.get(offset..).ok_or(BitcoinError::InsufficientBytes)
+}
+
+fn slice_at(bytes: &[u8], offset: usize, len: usize) -> Result<&[u8], BitcoinError> {
+    let end = offset
+        .checked_add(len)
+        .ok_or(BitcoinError::InsufficientBytes)?;
+    bytes
+        .get(offset..end)
+        .ok_or(BitcoinError::InsufficientBytes)
+}
+
+// Moves a parse offset forward. Sizes come from attacker-controlled length
+// prefixes, so an overflow (possible on 32-bit targets) is `InsufficientBytes`
+// rather than a wrapped offset.
+fn advance(offset: usize, by: usize) -> Result<usize, BitcoinError> {
+    offset
+        .checked_add(by)
+        .ok_or(BitcoinError::InsufficientBytes)
+}
+
+fn array_at<const N: usize>(bytes: &[u8], offset: usize) -> Result<[u8; N], BitcoinError> {
+    let mut buf = [0u8; N];
+    buf.copy_from_slice(slice_at(bytes, offset, N)?);
+    Ok(buf)
 }
 
 impl CompactSize {
+    pub const MAX: u64 = u64::MAX;
+    /// Largest values encodable in 1, 3 and 5 bytes; anything larger takes 9.
+    pub const MAX_SINGLE_BYTE: u64 = 0xFC;
+    pub const MAX_U16: u64 = 0xFFFF;
+    pub const MAX_U32: u64 = 0xFFFF_FFFF;
+    /// Marker bytes announcing a little-endian u16, u32 or u64 payload.
+    pub const PREFIX_U16: u8 = 0xFD;
+    pub const PREFIX_U32: u8 = 0xFE;
+    pub const PREFIX_U64: u8 = 0xFF;
+
     pub fn new(value: u64) -> Self {
         // TODO: Construct a CompactSize from a u64 value
         Self { value }
     }
 
+    /// The count prefix for `items`, e.g. the input count of a transaction.
+    pub fn count<T>(items: &[T]) -> CompactSize {
+        CompactSize::new(items.len() as u64)
+    }
+
+    /// The value as a length or count to index with. Nothing in a transaction
+    /// can legitimately need more than `u32::MAX`, so larger values are
+    /// `InvalidFormat` rather than truncated by `as usize` on 32-bit targets.
+    pub fn as_usize_checked(&self) -> Result<usize, BitcoinError> {
+        if self.value > Self::MAX_U32 {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        usize::try_from(self.value).map_err(|_| BitcoinError::InvalidFormat)
+    }
+
+    /// `None` if the sum overflows a u64.
+    pub fn checked_add(&self, rhs: u64) -> Option<CompactSize> {
+        self.value.checked_add(rhs).map(CompactSize::new)
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         // TODO: Encode according to Bitcoin's CompactSize format:
         // [0x00–0xFC] => 1 byte
         // [0xFDxxxx] => 0xFD + u16 (2 bytes)
         // [0xFExxxxxxxx] => 0xFE + u32 (4 bytes)
         // [0xFFxxxxxxxxxxxxxxxx] => 0xFF + u64 (8 bytes)
-        match self.value {
-            0..=0xFC => vec![self.value as u8],
-            0xFD..=0xFFFF => {
-                let mut bytes = vec![0xFD];
-                bytes.extend_from_slice(&(self.value as u16).to_le_bytes());
-                bytes
+        encode_to_vec(|e| self.encode(e))
+    }
+
+    /// Appends the serialization to `buf` and returns the number of bytes written.
+    pub fn encode_into(&self, buf: &mut Vec<u8>) -> usize {
+        append_to_vec(buf, |e| self.encode(e))
+    }
+
+    /// Number of bytes `to_bytes` would produce: 1, 3, 5 or 9.
+    pub fn encoded_len(&self) -> usize {
+        match self.prefix_byte() {
+            None => 1,
+            Some(Self::PREFIX_U16) => 3,
+            Some(Self::PREFIX_U32) => 5,
+            Some(_) => 9,
+        }
+    }
+
+    /// The marker byte preceding multi-byte encodings, or `None` when the
+    /// value fits in the single byte itself.
+    pub fn prefix_byte(&self) -> Option<u8> {
+        if self.value <= Self::MAX_SINGLE_BYTE {
+            None
+        } else if self.value <= Self::MAX_U16 {
+            Some(Self::PREFIX_U16)
+        } else if self.value <= Self::MAX_U32 {
+            Some(Self::PREFIX_U32)
+        } else {
+            Some(Self::PREFIX_U64)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<usize> {
+        self.encode(&mut IoEncoder(w))
+    }
+
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<usize, E::Error> {
+        match self.prefix_byte() {
+            None => e.emit(&[self.value as u8]).map(|_| 1),
+            Some(prefix @ Self::PREFIX_U16) => {
+                e.emit(&[prefix])?;
+                e.emit(&(self.value as u16).to_le_bytes())?;
+                Ok(3)
             }
-            0x10000..=0xFFFFFFFF => {
-                let mut bytes = vec![0xFE];
-                bytes.extend_from_slice(&(self.value as u32).to_le_bytes());
-                bytes
+            Some(prefix @ Self::PREFIX_U32) => {
+                e.emit(&[prefix])?;
+                e.emit(&(self.value as u32).to_le_bytes())?;
+                Ok(5)
             }
-            _ => {
-                let mut bytes = vec![0xFF];
-                bytes.extend_from_slice(&self.value.to_le_bytes());
-                bytes
+            Some(prefix) => {
+                e.emit(&[prefix])?;
+                e.emit(&self.value.to_le_bytes())?;
+                Ok(9)
             }
         }
     }
 
+    /// Decodes a CompactSize from its first byte alone when it is a single-byte
+    /// value (`0x00..=0xFC`); `None` means a prefix byte, so fall back to
+    /// `from_bytes`.
+    #[inline]
+    pub fn decode_u8_fast(byte: u8) -> Option<(CompactSize, usize)> {
+        (byte as u64 <= Self::MAX_SINGLE_BYTE).then(|| (CompactSize::new(byte as u64), 1))
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<(Self, &[u8]), BitcoinError> {
+        let (value, consumed) = Self::from_bytes(bytes)?;
+        Ok((value, &bytes[consumed..]))
+    }
+
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
         // TODO: Decode CompactSize, returning value and number of bytes consumed.
         // First check if bytes is empty.
         // Check that enough bytes are available based on prefix.
+        // Reject non-minimal encodings: each prefix must carry a value
+        // too large for the next shorter form.
         if bytes.is_empty() {
             return Err(BitcoinError::InsufficientBytes);
         }
@@ -59,6 +346,9 @@ impl CompactSize {
                     return Err(BitcoinError::InsufficientBytes);
                 }
                 let val = u16::from_le_bytes([bytes[1], bytes[2]]);
+                if val < 0xFD {
+                    return Err(BitcoinError::InvalidFormat);
+                }
                 Ok((CompactSize::new(val as u64), 3))
             }
             0xFE => {
@@ -66,6 +356,9 @@ impl CompactSize {
                     return Err(BitcoinError::InsufficientBytes);
                 }
                 let val = u32::from_le_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]);
+                if val < 0x10000 {
+                    return Err(BitcoinError::InvalidFormat);
+                }
                 Ok((CompactSize::new(val as u64), 5))
             }
             0xFF => {
@@ -75,25 +368,126 @@ impl CompactSize {
                 let val = u64::from_le_bytes([
                     bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7], bytes[8],
                 ]);
+                if val < 0x100000000 {
+                    return Err(BitcoinError::InvalidFormat);
+                }
                 Ok((CompactSize::new(val), 9))
             }
         }
     }
+
+    #[cfg(feature = "std")]
+    pub fn read_from<R: Read>(r: &mut R) -> Result<Self, BitcoinError> {
+        let [prefix] = read_array(r)?;
+        Self::read_with_prefix(prefix, r)
+    }
+
+    // Finishes decoding once the prefix byte has already been consumed.
+    #[cfg(feature = "std")]
+    fn read_with_prefix<R: Read>(prefix: u8, r: &mut R) -> Result<Self, BitcoinError> {
+        let len = match prefix {
+            0xFD => 2,
+            0xFE => 4,
+            0xFF => 8,
+            _ => 0,
+        };
+        let mut buf = [0u8; 9];
+        buf[0] = prefix;
+        r.read_exact(&mut buf[1..=len])?;
+        Ok(Self::from_bytes(&buf[..=len])?.0)
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+impl From<u64> for CompactSize {
+    fn from(value: u64) -> Self {
+        CompactSize::new(value)
+    }
+}
+
+impl From<CompactSize> for u64 {
+    fn from(cs: CompactSize) -> Self {
+        cs.value
+    }
+}
+
+/// A transaction hash. `.0` is in internal byte order, as hashed and as it
+/// appears on the wire; explorers and RPCs show it reversed ("big-endian").
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
 pub struct Txid(pub [u8; 32]);
 
+impl Txid {
+    pub fn null() -> Self {
+        Txid([0u8; 32])
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.0 == [0u8; 32]
+    }
+
+    /// Copies a 32-byte internal-order hash; any other length is `InvalidFormat`.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, BitcoinError> {
+        <[u8; 32]>::try_from(bytes)
+            .map(Txid)
+            .map_err(|_| BitcoinError::InvalidFormat)
+    }
+
+    /// The bytes in display order (reversed), as Stratum and some RPCs expect.
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        let mut bytes = self.0;
+        bytes.reverse();
+        bytes
+    }
+
+    /// Inverse of `to_be_bytes`: takes display-order bytes.
+    pub fn from_be_bytes(mut bytes: [u8; 32]) -> Txid {
+        bytes.reverse();
+        Txid(bytes)
+    }
+
+    /// Hex in display order (byte-reversed), as shown by block explorers and bitcoind.
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.to_be_bytes())
+    }
+
+    /// Parses display-order hex (byte-reversed) back into internal byte order.
+    /// Anything other than exactly 64 hex characters is `InvalidFormat`.
+    pub fn from_hex(s: &str) -> Result<Self, BitcoinError> {
+        if s.len() != 64 {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let mut arr = [0u8; 32];
+        hex::decode_to_slice(s, &mut arr).map_err(|_| BitcoinError::InvalidFormat)?;
+        arr.reverse();
+        Ok(Txid(arr))
+    }
+}
+
+impl fmt::Display for Txid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl FromStr for Txid {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Txid::from_hex(s)
+    }
+}
+
+#[cfg(feature = "serde")]
 impl Serialize for Txid {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
         // TODO: Serialize as a hex-encoded string (32 bytes => 64 hex characters)
-        serializer.serialize_str(&hex::encode(self.0))
+        serializer.serialize_str(&self.to_hex())
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for Txid {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -108,12 +502,13 @@ impl<'de> Deserialize<'de> for Txid {
         }
         let mut arr = [0u8; 32];
         arr.copy_from_slice(&bytes);
+        arr.reverse();
         Ok(Txid(arr))
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
-
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct OutPoint {
     pub txid: Txid,
     pub vout: u32,
@@ -130,25 +525,92 @@ impl OutPoint {
 
     pub fn to_bytes(&self) -> Vec<u8> {
         // TODO: Serialize as: txid (32 bytes) + vout (4 bytes, little-endian)
-        let mut bytes = self.txid.0.to_vec();
-        bytes.extend_from_slice(&self.vout.to_le_bytes());
-        bytes
+        encode_to_vec(|e| self.encode(e))
+    }
+
+    /// Appends the serialization to `buf` and returns the number of bytes written.
+    pub fn encode_into(&self, buf: &mut Vec<u8>) -> usize {
+        append_to_vec(buf, |e| self.encode(e))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<usize> {
+        self.encode(&mut IoEncoder(w))
+    }
+
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<usize, E::Error> {
+        e.emit(&self.txid.0)?;
+        e.emit(&self.vout.to_le_bytes())?;
+        Ok(36)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<(Self, &[u8]), BitcoinError> {
+        let (value, consumed) = Self::from_bytes(bytes)?;
+        Ok((value, &bytes[consumed..]))
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
         // TODO: Deserialize 36 bytes: txid[0..32], vout[32..36]
         // Return error if insufficient bytes
-        if bytes.len() < 36 {
-            return Err(BitcoinError::InsufficientBytes);
-        }
-        let mut txid_bytes = [0u8; 32];
-        txid_bytes.copy_from_slice(&bytes[0..32]);
-        let vout = u32::from_le_bytes([bytes[32], bytes[33], bytes[34], bytes[35]]);
+        let txid_bytes = array_at(bytes, 0)?;
+        let vout = u32::from_le_bytes(array_at(bytes, 32)?);
         Ok((OutPoint::new(txid_bytes, vout), 36))
     }
+
+    /// The outpoint referenced by coinbase inputs: all-zero txid and vout 0xFFFFFFFF.
+    pub fn is_null(&self) -> bool {
+        self.txid.is_null() && self.vout == u32::MAX
+    }
+
+    /// Canonical string key for UTXO databases, `<txid>:<vout>` with the txid
+    /// in display order. Same text as `Display`.
+    pub fn to_key(&self) -> String {
+        self.to_string()
+    }
+
+    /// Parses a key produced by `to_key`. Unlike `FromStr`, only the canonical
+    /// form is accepted (lowercase hex, no leading zeros or `+` in `vout`), so
+    /// each outpoint has exactly one key.
+    pub fn from_key(key: &str) -> Result<Self, BitcoinError> {
+        let outpoint: OutPoint = key.parse()?;
+        if outpoint.to_key() != key {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(outpoint)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn read_from<R: Read>(r: &mut R) -> Result<Self, BitcoinError> {
+        let buf: [u8; 36] = read_array(r)?;
+        Ok(Self::from_bytes(&buf)?.0)
+    }
+}
+
+impl fmt::Display for OutPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.txid, self.vout)
+    }
+}
+
+impl FromStr for OutPoint {
+    type Err = BitcoinError;
+
+    // Parses `<txid>:<vout>` with the txid in display (byte-reversed) order.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (txid, vout) = s.rsplit_once(':').ok_or(BitcoinError::InvalidFormat)?;
+        let txid = Txid::from_hex(txid)?;
+        let vout = vout.parse().map_err(|_| BitcoinError::InvalidFormat)?;
+        Ok(OutPoint { txid, vout })
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+/// Consensus limit on the length of a script being executed. Longer scripts
+/// are still valid to create (bitcoind treats such outputs as unspendable), so
+/// parsing doesn't enforce it; use `ParseLimits` to cap script lengths.
+pub const MAX_SCRIPT_SIZE: usize = 10_000;
+
+/// A script. With serde it is a hex string, like bitcoind's `scriptPubKey.hex`.
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Script {
     pub bytes: Vec<u8>,
 }
@@ -159,157 +621,2356 @@ impl Script {
         Self { bytes }
     }
 
+    pub fn builder() -> ScriptBuilder {
+        ScriptBuilder::new()
+    }
+
+    /// The script itself, without the length prefix `to_bytes` adds.
+    pub fn raw_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Length of `to_bytes()`: the CompactSize prefix plus the script.
+    pub fn prefixed_len(&self) -> usize {
+        CompactSize::new(self.bytes.len() as u64).encoded_len() + self.bytes.len()
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         // TODO: Prefix with CompactSize (length), then raw bytes
-        let mut product = CompactSize::new(self.bytes.len() as u64).to_bytes();
-        product.extend_from_slice(&self.bytes);
-        product
+        encode_to_vec(|e| self.encode(e))
+    }
+
+    /// Appends the serialization to `buf` and returns the number of bytes written.
+    pub fn encode_into(&self, buf: &mut Vec<u8>) -> usize {
+        append_to_vec(buf, |e| self.encode(e))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<usize> {
+        self.encode(&mut IoEncoder(w))
+    }
+
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<usize, E::Error> {
+        let prefix = CompactSize::new(self.bytes.len() as u64).encode(e)?;
+        e.emit(&self.bytes)?;
+        Ok(prefix + self.bytes.len())
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<(Self, &[u8]), BitcoinError> {
+        let (value, consumed) = Self::from_bytes(bytes)?;
+        Ok((value, &bytes[consumed..]))
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
         // TODO: Parse CompactSize prefix, then read that many bytes
         // Return error if not enough bytes
-        let (prefix, consumed) = CompactSize::from_bytes(bytes)?;
-        let length = prefix.value as usize;
-        if bytes.len() < consumed + length {
-            return Err(BitcoinError::InsufficientBytes);
-        }
-        let script_bytes = bytes[consumed..consumed + length].to_vec();
-        Ok((Script::new(script_bytes), consumed + length))
+        let (script, consumed) = Self::from_bytes_ref(bytes)?;
+        Ok((script.to_script(), consumed))
     }
-}
 
-impl Deref for Script {
-    type Target = Vec<u8>;
-    fn deref(&self) -> &Self::Target {
-        // TODO: Allow &Script to be used as &[u8]
-        &self.bytes
+    /// Like `from_bytes`, but borrows the script bytes from `bytes`.
+    pub fn from_bytes_ref(bytes: &[u8]) -> Result<(ScriptRef<'_>, usize), BitcoinError> {
+        let (prefix, consumed) = CompactSize::from_bytes(bytes)?;
+        let length = prefix.as_usize_checked()?;
+        Ok((
+            ScriptRef(slice_at(bytes, consumed, length)?),
+            advance(consumed, length)?,
+        ))
     }
-}
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
-pub struct TransactionInput {
-    pub previous_output: OutPoint,
-    pub script_sig: Script,
-    pub sequence: u32,
+    #[cfg(feature = "std")]
+    pub fn read_from<R: Read>(r: &mut R) -> Result<Self, BitcoinError> {
+        let length = CompactSize::read_from(r)?;
+        Ok(Script::new(read_vec(r, length.value)?))
+    }
 }
 
-impl TransactionInput {
-    pub fn new(previous_output: OutPoint, script_sig: Script, sequence: u32) -> Self {
-        // TODO: Basic constructor
-        Self {
-            previous_output,
-            script_sig,
-            sequence,
-        }
+impl Script {
+    fn instructions(&self) -> Instructions<'_> {
+        Instructions { bytes: &self.bytes }
     }
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        // TODO: Serialize: OutPoint + Script (with CompactSize) + sequence (4 bytes LE)
-        let mut bytes = self.previous_output.to_bytes();
-        bytes.extend_from_slice(&self.script_sig.to_bytes());
-        bytes.extend_from_slice(&self.sequence.to_le_bytes());
-        bytes
+    /// Disassembles the script the way bitcoind does: opcode names, pushes of
+    /// up to 4 bytes as script numbers, longer pushes as hex, and `[error]`
+    /// in place of truncated push data.
+    pub fn to_asm(&self) -> String {
+        self.asm(false)
     }
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        // TODO: Deserialize in order:
-        // - OutPoint (36 bytes)
-        // - Script (with CompactSize)
-        // - Sequence (4 bytes)
-        let (outpoint, use1) = OutPoint::from_bytes(bytes)?;
-        let (script, use2) = Script::from_bytes(&bytes[use1..])?;
-        if bytes.len() < use1 + use2 + 4 {
-            return Err(BitcoinError::InsufficientBytes);
+    // With `decode_sighash`, pushes that are strictly encoded signatures end in
+    // their sighash name, e.g. `3044...[ALL]`, as bitcoind shows scriptSigs.
+    fn asm(&self, decode_sighash: bool) -> String {
+        let decode_sighash = decode_sighash && !self.is_op_return();
+        let mut parts = Vec::new();
+        for instruction in self.instructions() {
+            match instruction {
+                Ok(Instruction::Push(data)) if data.len() <= 4 => {
+                    parts.push(decode_script_num(data).to_string())
+                }
+                Ok(Instruction::Push(data)) => match sighash_name(data) {
+                    Some(name) if decode_sighash => {
+                        let sig = hex::encode(&data[..data.len() - 1]);
+                        parts.push(alloc::format!("{}[{}]", sig, name))
+                    }
+                    _ => parts.push(hex::encode(data)),
+                },
+                Ok(Instruction::Op(op)) => parts.push(opcodes::name(op).to_string()),
+                Err(_) => {
+                    parts.push("[error]".to_string());
+                    break;
+                }
+            }
         }
-        let sequence = u32::from_le_bytes([
-            bytes[use1 + use2],
-            bytes[use1 + use2 + 1],
-            bytes[use1 + use2 + 2],
-            bytes[use1 + use2 + 3],
-        ]);
-        Ok((
-            TransactionInput {
-                previous_output: outpoint,
-                script_sig: script,
-                sequence,
-            },
-            use1 + use2 + 4,
-        ))
+        parts.join(" ")
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
-pub struct BitcoinTransaction {
-    pub version: u32,
-    pub inputs: Vec<TransactionInput>,
-    pub lock_time: u32,
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AddressType {
+    P2pkh,
+    P2sh,
+    P2wpkh,
+    P2wsh,
 }
 
-impl BitcoinTransaction {
-    pub fn new(version: u32, inputs: Vec<TransactionInput>, lock_time: u32) -> Self {
-        // TODO: Construct a transaction from parts
-        Self {
-            version,
-            inputs,
-            lock_time,
+/// The standard output templates, as bitcoind's `Solver` recognizes them.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ScriptType {
+    P2pk,
+    P2pkh,
+    P2sh,
+    P2wpkh,
+    P2wsh,
+    P2tr,
+    OpReturn,
+    Multisig,
+    NonStandard,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Network {
+    Bitcoin,
+    Testnet,
+    Regtest,
+    Signet,
+}
+
+impl Network {
+    // Base58check version bytes for P2PKH and P2SH addresses.
+    fn base58_prefixes(self) -> (u8, u8) {
+        match self {
+            Network::Bitcoin => (0x00, 0x05),
+            Network::Testnet | Network::Regtest | Network::Signet => (0x6f, 0xc4),
         }
     }
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        // TODO: Format:
-        // - version (4 bytes LE)
-        // - CompactSize (number of inputs)
-        // - each input serialized
-        // - lock_time (4 bytes LE)
-        let mut bytes = self.version.to_le_bytes().to_vec();
-        bytes.extend_from_slice(&CompactSize::new(self.inputs.len() as u64).to_bytes());
-        for input in &self.inputs {
-            bytes.extend_from_slice(&input.to_bytes());
+    fn bech32_hrp(self) -> bech32::Hrp {
+        match self {
+            Network::Bitcoin => bech32::hrp::BC,
+            Network::Testnet | Network::Signet => bech32::hrp::TB,
+            Network::Regtest => bech32::hrp::BCRT,
         }
-        bytes.extend_from_slice(&self.lock_time.to_le_bytes());
-        bytes
     }
+}
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        // TODO: Read version, CompactSize for input count
-        // Parse inputs one by one
-        // Read final 4 bytes for lock_time
-        if bytes.len() < 4 {
-            return Err(BitcoinError::InsufficientBytes);
-        }
-        let version = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
-        let (input_count, consumed) = CompactSize::from_bytes(&bytes[4..])?;
-        let mut inputs = Vec::with_capacity(input_count.value as usize);
-        let mut offset = 4 + consumed;
-        for _ in 0..input_count.value {
-            let (input, input_size) = TransactionInput::from_bytes(&bytes[offset..])?;
-            inputs.push(input);
-            offset += input_size;
-        }
-        if bytes.len() < offset + 4 {
-            return Err(BitcoinError::InsufficientBytes);
+impl Script {
+    /// Matches the script against every standard template. Only scripts
+    /// shaped like bare multisig are decoded; the rest are byte comparisons.
+    pub fn classify(&self) -> ScriptType {
+        let b = self.bytes.as_slice();
+        if self.is_p2pkh() {
+            ScriptType::P2pkh
+        } else if self.is_p2sh() {
+            ScriptType::P2sh
+        } else if self.is_p2wpkh() {
+            ScriptType::P2wpkh
+        } else if self.is_p2wsh() {
+            ScriptType::P2wsh
+        } else if matches!(*b, [OP_1, 32, ..] if b.len() == 34) {
+            // OP_1 <32 bytes>
+            ScriptType::P2tr
+        } else if self.is_op_return() {
+            ScriptType::OpReturn
+        } else if matches!(*b, [len @ (33 | 65), .., OP_CHECKSIG] if b.len() == len as usize + 2) {
+            // <33 or 65 byte pubkey> OP_CHECKSIG
+            ScriptType::P2pk
+        } else if self.multisig_keys().is_some() {
+            ScriptType::Multisig
+        } else {
+            ScriptType::NonStandard
         }
-        let lock_time = u32::from_le_bytes([
-            bytes[offset],
-            bytes[offset + 1],
-            bytes[offset + 2],
-            bytes[offset + 3],
-        ]);
-        Ok((
-            BitcoinTransaction {
-                version,
-                inputs,
-                lock_time,
-            },
-            offset + 4,
-        ))
     }
-}
 
-impl fmt::Display for BitcoinTransaction {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // TODO: Format a user-friendly string showing version, inputs, lock_time
+    // OP_DUP OP_HASH160 <20 bytes> OP_EQUALVERIFY OP_CHECKSIG
+    pub fn is_p2pkh(&self) -> bool {
+        let b = &self.bytes;
+        b.len() == 25
+            && b[0] == OP_DUP
+            && b[1] == OP_HASH160
+            && b[2] == 20
+            && b[23] == OP_EQUALVERIFY
+            && b[24] == OP_CHECKSIG
+    }
+
+    // OP_HASH160 <20 bytes> OP_EQUAL
+    pub fn is_p2sh(&self) -> bool {
+        let b = &self.bytes;
+        b.len() == 23 && b[0] == OP_HASH160 && b[1] == 20 && b[22] == OP_EQUAL
+    }
+
+    // OP_0 <20 bytes>
+    pub fn is_p2wpkh(&self) -> bool {
+        let b = &self.bytes;
+        b.len() == 22 && b[0] == OP_0 && b[1] == 20
+    }
+
+    // OP_0 <32 bytes>
+    pub fn is_p2wsh(&self) -> bool {
+        let b = &self.bytes;
+        b.len() == 34 && b[0] == OP_0 && b[1] == 32
+    }
+
+    pub fn is_op_return(&self) -> bool {
+        self.bytes.first() == Some(&OP_RETURN)
+    }
+
+    /// The required signature count `m` and the public keys of an
+    /// `OP_m <pubkey>... OP_n OP_CHECKMULTISIG` script, or `None` if the
+    /// script doesn't match or pushes a different number of keys than `n`.
+    pub fn multisig_info(&self) -> Option<(u8, Vec<Vec<u8>>)> {
+        let (m, keys) = self.multisig_keys()?;
+        Some((m, keys.into_iter().map(<[u8]>::to_vec).collect()))
+    }
+
+    // OP_m <pubkey>... OP_n OP_CHECKMULTISIG with n 33- or 65-byte keys and
+    // 1 <= m <= n <= 16. Returns m and the keys.
+    fn multisig_keys(&self) -> Option<(u8, Vec<&[u8]>)> {
+        // Cheap template check before decoding anything
+        if !matches!(
+            self.bytes[..],
+            [OP_1..=OP_16, .., OP_1..=OP_16, OP_CHECKMULTISIG]
+        ) {
+            return None;
+        }
+        let instructions: Vec<Instruction> = self.instructions().collect::<Result<_, _>>().ok()?;
+        let [
+            Instruction::Op(m @ OP_1..=OP_16),
+            ref keys @ ..,
+            Instruction::Op(n @ OP_1..=OP_16),
+            Instruction::Op(OP_CHECKMULTISIG),
+        ] = *instructions.as_slice()
+        else {
+            return None;
+        };
+        let (m, n) = (m - OP_1 + 1, n - OP_1 + 1);
+        let keys = keys
+            .iter()
+            .map(|instruction| match *instruction {
+                Instruction::Push(key) if matches!(key.len(), 33 | 65) => Some(key),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        (m <= n && keys.len() == n as usize).then_some((m, keys))
+    }
+
+    /// Payload of an `OP_RETURN <push>` script, borrowed from the script.
+    pub fn op_return_data(&self) -> Option<&[u8]> {
+        let rest = self.bytes.strip_prefix(&[OP_RETURN])?;
+        let mut instructions = Instructions { bytes: rest };
+        match (instructions.next(), instructions.next()) {
+            (Some(Ok(Instruction::Push(data))), None) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Witness version and program of a BIP141 output script: a version
+    /// opcode (`OP_0`, `OP_1`..`OP_16`) followed by a single 2–40 byte push.
+    pub fn witness_program(&self) -> Option<(u8, Vec<u8>)> {
+        let b = &self.bytes;
+        if b.len() < 4 || b.len() > 42 || b[1] as usize != b.len() - 2 {
+            return None;
+        }
+        let version = match b[0] {
+            OP_0 => 0,
+            OP_1..=OP_16 => b[0] - OP_1 + 1,
+            _ => return None,
+        };
+        Some((version, b[2..].to_vec()))
+    }
+
+    /// The address paying to this script on `network`: base58check for P2PKH
+    /// and P2SH, bech32 for v0 and bech32m for later witness programs. `None`
+    /// for other scripts.
+    pub fn address(&self, network: Network) -> Option<String> {
+        let (p2pkh_prefix, p2sh_prefix) = network.base58_prefixes();
+        if let Some(hash) = self.p2pkh_hash() {
+            return Some(base58check_encode(p2pkh_prefix, &hash));
+        }
+        if let Some(hash) = self.p2sh_hash() {
+            return Some(base58check_encode(p2sh_prefix, &hash));
+        }
+        let (version, program) = self.witness_program()?;
+        let version = bech32::Fe32::try_from(version).ok()?;
+        bech32::segwit::encode(network.bech32_hrp(), version, &program).ok()
+    }
+
+    /// The 32-byte x-only output key of a P2TR (`OP_1 <32 bytes>`) script.
+    pub fn taproot_output_key(&self) -> Option<[u8; 32]> {
+        if self.classify() != ScriptType::P2tr {
+            return None;
+        }
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&self.bytes[2..34]);
+        Some(key)
+    }
+
+    pub fn p2pkh_hash(&self) -> Option<[u8; 20]> {
+        if !self.is_p2pkh() {
+            return None;
+        }
+        let mut hash = [0u8; 20];
+        hash.copy_from_slice(&self.bytes[3..23]);
+        Some(hash)
+    }
+
+    pub fn p2sh_hash(&self) -> Option<[u8; 20]> {
+        if !self.is_p2sh() {
+            return None;
+        }
+        let mut hash = [0u8; 20];
+        hash.copy_from_slice(&self.bytes[2..22]);
+        Some(hash)
+    }
+
+    pub fn address_type(&self) -> Option<AddressType> {
+        if self.is_p2pkh() {
+            Some(AddressType::P2pkh)
+        } else if self.is_p2sh() {
+            Some(AddressType::P2sh)
+        } else if self.is_p2wpkh() {
+            Some(AddressType::P2wpkh)
+        } else if self.is_p2wsh() {
+            Some(AddressType::P2wsh)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Instruction<'a> {
+    Push(&'a [u8]),
+    Op(u8),
+}
+
+// Walks a script one opcode at a time; yields a single error and stops if
+// push data runs past the end of the script.
+struct Instructions<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = Result<Instruction<'a>, BitcoinError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&op, rest) = self.bytes.split_first()?;
+        let (len, rest) = match op {
+            OP_0..OP_PUSHDATA1 => (op as usize, rest),
+            OP_PUSHDATA1 => match rest.split_first() {
+                Some((&len, rest)) => (len as usize, rest),
+                None => return self.fail(),
+            },
+            OP_PUSHDATA2 if rest.len() >= 2 => {
+                (u16::from_le_bytes([rest[0], rest[1]]) as usize, &rest[2..])
+            }
+            OP_PUSHDATA4 if rest.len() >= 4 => (
+                u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize,
+                &rest[4..],
+            ),
+            OP_PUSHDATA2 | OP_PUSHDATA4 => return self.fail(),
+            _ => {
+                self.bytes = rest;
+                return Some(Ok(Instruction::Op(op)));
+            }
+        };
+        if rest.len() < len {
+            return self.fail();
+        }
+        let (data, rest) = rest.split_at(len);
+        self.bytes = rest;
+        Some(Ok(Instruction::Push(data)))
+    }
+}
+
+impl Script {
+    /// Compares the decoded opcodes and pushed data rather than raw bytes, so
+    /// the same data pushed with a direct length or `OP_PUSHDATA1/2/4`
+    /// compares equal. Malformed scripts only match byte-for-byte.
+    pub fn equals_semantically(&self, other: &Script) -> bool {
+        let mut ours = self.instructions();
+        let mut theirs = other.instructions();
+        loop {
+            match (ours.next(), theirs.next()) {
+                (None, None) => return true,
+                (Some(Ok(a)), Some(Ok(b))) if a == b => {}
+                (Some(Err(_)), _) | (_, Some(Err(_))) => return self.bytes == other.bytes,
+                _ => return false,
+            }
+        }
+    }
+
+    /// Signature operations as bitcoind counts them: one per `OP_CHECKSIG(VERIFY)`
+    /// and 20 per `OP_CHECKMULTISIG(VERIFY)`, or with `accurate` the key count
+    /// when an `OP_1`..`OP_16` precedes it. Counting stops at a truncated push.
+    pub fn sigop_count(&self, accurate: bool) -> usize {
+        const MAX_PUBKEYS_PER_MULTISIG: usize = 20;
+        let mut count = 0;
+        let mut last = None;
+        for instruction in self.instructions() {
+            let Ok(instruction) = instruction else {
+                break;
+            };
+            match instruction {
+                Instruction::Op(OP_CHECKSIG | OP_CHECKSIGVERIFY) => count += 1,
+                Instruction::Op(OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY) => {
+                    count += match last {
+                        Some(Instruction::Op(n @ OP_1..=OP_16)) if accurate => {
+                            (n - OP_1 + 1) as usize
+                        }
+                        _ => MAX_PUBKEYS_PER_MULTISIG,
+                    }
+                }
+                _ => {}
+            }
+            last = Some(instruction);
+        }
+        count
+    }
+
+    // The script with every OP_CODESEPARATOR removed, as signed by legacy
+    // sighash. Anything after a truncated push is kept verbatim.
+    fn without_codeseparators(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.bytes.len());
+        let mut instructions = self.instructions();
+        loop {
+            let start = self.bytes.len() - instructions.bytes.len();
+            match instructions.next() {
+                None => break,
+                Some(Err(_)) => {
+                    out.extend_from_slice(&self.bytes[start..]);
+                    break;
+                }
+                Some(Ok(Instruction::Op(OP_CODESEPARATOR))) => {}
+                Some(Ok(_)) => {
+                    let end = self.bytes.len() - instructions.bytes.len();
+                    out.extend_from_slice(&self.bytes[start..end]);
+                }
+            }
+        }
+        out
+    }
+}
+
+impl Instructions<'_> {
+    fn fail<T>(&mut self) -> Option<Result<T, BitcoinError>> {
+        self.bytes = &[];
+        Some(Err(BitcoinError::InsufficientBytes))
+    }
+}
+
+// Sighash name of a strictly DER-encoded signature with a defined sighash byte.
+fn sighash_name(sig: &[u8]) -> Option<&'static str> {
+    if !is_valid_signature_encoding(sig) {
+        return None;
+    }
+    let name = match *sig.last()? as u32 {
+        SIGHASH_ALL => "ALL",
+        SIGHASH_NONE => "NONE",
+        SIGHASH_SINGLE => "SINGLE",
+        0x81 => "ALL|ANYONECANPAY",
+        0x82 => "NONE|ANYONECANPAY",
+        0x83 => "SINGLE|ANYONECANPAY",
+        _ => return None,
+    };
+    Some(name)
+}
+
+// BIP66 strict DER: 0x30 len 0x02 lenR R 0x02 lenS S sighash, with minimal,
+// non-negative R and S.
+fn is_valid_signature_encoding(sig: &[u8]) -> bool {
+    let size = sig.len();
+    if !(9..=73).contains(&size) || sig[0] != 0x30 || sig[1] as usize != size - 3 {
+        return false;
+    }
+    let len_r = sig[3] as usize;
+    if 5 + len_r >= size {
+        return false;
+    }
+    let len_s = sig[5 + len_r] as usize;
+    if len_r + len_s + 7 != size {
+        return false;
+    }
+    let r = &sig[4..4 + len_r];
+    let s = &sig[6 + len_r..6 + len_r + len_s];
+    sig[2] == 0x02
+        && sig[4 + len_r] == 0x02
+        && [r, s].iter().all(|int| {
+            !int.is_empty()
+                && int[0] & 0x80 == 0
+                && !(int.len() > 1 && int[0] == 0 && int[1] & 0x80 == 0)
+        })
+}
+
+// Little-endian, sign-magnitude integer as used by script arithmetic.
+fn decode_script_num(data: &[u8]) -> i64 {
+    let Some((&last, _)) = data.split_last() else {
+        return 0;
+    };
+    let mut value: i64 = 0;
+    for (i, byte) in data.iter().enumerate() {
+        value |= (*byte as i64) << (8 * i);
+    }
+    if last & 0x80 != 0 {
+        -(value & !(0x80 << (8 * (data.len() - 1))))
+    } else {
+        value
+    }
+}
+
+// Minimal encoding of `value` for `decode_script_num`; zero is empty.
+fn encode_script_num(value: i64) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut abs = value.unsigned_abs();
+    while abs > 0 {
+        out.push(abs as u8);
+        abs >>= 8;
+    }
+    // The top bit of the last byte is the sign, so add a byte if it's taken
+    match out.last_mut() {
+        Some(last) if *last & 0x80 != 0 => out.push(if value < 0 { 0x80 } else { 0 }),
+        Some(last) if value < 0 => *last |= 0x80,
+        _ => {}
+    }
+    out
+}
+
+impl Deref for Script {
+    type Target = Vec<u8>;
+    fn deref(&self) -> &Self::Target {
+        // TODO: Allow &Script to be used as &[u8]
+        &self.bytes
+    }
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_asm())
+    }
+}
+
+impl AsRef<[u8]> for Script {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl From<Vec<u8>> for Script {
+    fn from(bytes: Vec<u8>) -> Self {
+        Script::new(bytes)
+    }
+}
+
+impl From<&[u8]> for Script {
+    fn from(bytes: &[u8]) -> Self {
+        Script::new(bytes.to_vec())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Script {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&hex::encode(&self.bytes))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Script {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        let bytes = hex::decode(s).map_err(serde::de::Error::custom)?;
+        Ok(Script::new(bytes))
+    }
+}
+
+/// A script borrowed from a serialized buffer, for parsing without copying.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ScriptRef<'a>(pub &'a [u8]);
+
+impl ScriptRef<'_> {
+    pub fn to_script(&self) -> Script {
+        Script::new(self.0.to_vec())
+    }
+}
+
+impl AsRef<[u8]> for ScriptRef<'_> {
+    fn as_ref(&self) -> &[u8] {
+        self.0
+    }
+}
+
+/// Assembles a script from opcodes and data pushes.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptBuilder {
+    bytes: Vec<u8>,
+}
+
+impl ScriptBuilder {
+    pub fn new() -> Self {
+        Self { bytes: Vec::new() }
+    }
+
+    pub fn push_opcode(mut self, op: u8) -> Self {
+        self.bytes.push(op);
+        self
+    }
+
+    /// Pushes `data` with the shortest length prefix: a direct length byte up
+    /// to 75 bytes, then `OP_PUSHDATA1`, `OP_PUSHDATA2` or `OP_PUSHDATA4`.
+    pub fn push_slice(mut self, data: &[u8]) -> Self {
+        let len = data.len();
+        if len < OP_PUSHDATA1 as usize {
+            self.bytes.push(len as u8);
+        } else if len <= 0xFF {
+            self.bytes.push(OP_PUSHDATA1);
+            self.bytes.push(len as u8);
+        } else if len <= 0xFFFF {
+            self.bytes.push(OP_PUSHDATA2);
+            self.bytes.extend_from_slice(&(len as u16).to_le_bytes());
+        } else {
+            self.bytes.push(OP_PUSHDATA4);
+            self.bytes.extend_from_slice(&(len as u32).to_le_bytes());
+        }
+        self.bytes.extend_from_slice(data);
+        self
+    }
+
+    /// Pushes a script number the way bitcoind does: `OP_0`, `OP_1NEGATE` and
+    /// `OP_1`..`OP_16` for small values, otherwise its minimal encoding.
+    pub fn push_int(self, value: i64) -> Self {
+        match value {
+            0 => self.push_opcode(OP_0),
+            -1 => self.push_opcode(OP_1NEGATE),
+            1..=16 => self.push_opcode(OP_1 + value as u8 - 1),
+            _ => self.push_slice(&encode_script_num(value)),
+        }
+    }
+
+    pub fn into_script(self) -> Script {
+        Script::new(self.bytes)
+    }
+}
+
+/// With serde the witness is a list of hex strings, like bitcoind's
+/// `txinwitness`, and may be omitted when empty.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TransactionInput {
+    pub previous_output: OutPoint,
+    pub script_sig: Script,
+    pub sequence: u32,
+    #[cfg_attr(feature = "serde", serde(default, with = "witness_hex"))]
+    pub witness: Vec<Vec<u8>>,
+}
+
+#[cfg(feature = "serde")]
+mod witness_hex {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(witness: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(witness.iter().map(hex::encode))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Vec<u8>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|item| hex::decode(item).map_err(serde::de::Error::custom))
+            .collect()
+    }
+}
+
+impl TransactionInput {
+    pub fn new(previous_output: OutPoint, script_sig: Script, sequence: u32) -> Self {
+        // TODO: Basic constructor
+        Self {
+            previous_output,
+            script_sig,
+            sequence,
+            witness: Vec::new(),
+        }
+    }
+
+    pub fn with_witness(mut self, witness: Vec<Vec<u8>>) -> Self {
+        self.witness = witness;
+        self
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // TODO: Serialize: OutPoint + Script (with CompactSize) + sequence (4 bytes LE)
+        encode_to_vec(|e| self.encode(e))
+    }
+
+    /// Appends the serialization to `buf` and returns the number of bytes written.
+    pub fn encode_into(&self, buf: &mut Vec<u8>) -> usize {
+        append_to_vec(buf, |e| self.encode(e))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<usize> {
+        self.encode(&mut IoEncoder(w))
+    }
+
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<usize, E::Error> {
+        let mut written = self.previous_output.encode(e)?;
+        written += self.script_sig.encode(e)?;
+        e.emit(&self.sequence.to_le_bytes())?;
+        Ok(written + 4)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<(Self, &[u8]), BitcoinError> {
+        let (value, consumed) = Self::from_bytes(bytes)?;
+        Ok((value, &bytes[consumed..]))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        // TODO: Deserialize in order:
+        // - OutPoint (36 bytes)
+        // - Script (with CompactSize)
+        // - Sequence (4 bytes)
+        let (input, consumed) = Self::from_bytes_ref(bytes)?;
+        Ok((input.to_input(), consumed))
+    }
+
+    /// Lazily parses `count` consecutive inputs, yielding each with its size.
+    /// Stops after the first error, which carries the offset within `bytes`.
+    pub fn parse_many(
+        bytes: &[u8],
+        count: usize,
+    ) -> impl Iterator<Item = Result<(TransactionInput, usize), BitcoinError>> + '_ {
+        let mut offset = 0;
+        let mut failed = false;
+        (0..count).map_while(move |_| {
+            if failed {
+                return None;
+            }
+            let item = slice_from(bytes, offset)
+                .and_then(Self::from_bytes)
+                .map_err(|e| e.at(offset, "input"));
+            match &item {
+                Ok((_, size)) => match advance(offset, *size) {
+                    Ok(next) => offset = next,
+                    Err(_) => failed = true,
+                },
+                Err(_) => failed = true,
+            }
+            Some(item)
+        })
+    }
+
+    /// Like `from_bytes`, but the scriptSig borrows from `bytes`.
+    pub fn from_bytes_ref(bytes: &[u8]) -> Result<(TransactionInputRef<'_>, usize), BitcoinError> {
+        let (outpoint, use1) =
+            OutPoint::from_bytes(bytes).map_err(|e| e.at(0, "previous output"))?;
+        let (script, use2) = slice_from(bytes, use1)
+            .and_then(Script::from_bytes_ref)
+            .map_err(|e| e.at(use1, "script_sig"))?;
+        let sequence = array_at(bytes, use1 + use2)
+            .map(u32::from_le_bytes)
+            .map_err(|e| e.at(use1 + use2, "sequence"))?;
+        Ok((
+            TransactionInputRef {
+                previous_output: outpoint,
+                script_sig: script,
+                sequence,
+            },
+            use1 + use2 + 4,
+        ))
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.sequence == u32::MAX
+    }
+
+    /// BIP125: any sequence below 0xFFFFFFFE signals replaceability.
+    pub fn enables_rbf(&self) -> bool {
+        self.sequence < u32::MAX - 1
+    }
+
+    /// BIP34 block height from the first push of a coinbase scriptSig, or
+    /// `None` for non-coinbase inputs and malformed or negative pushes.
+    pub fn coinbase_height(&self) -> Option<u32> {
+        if !self.previous_output.is_null() {
+            return None;
+        }
+        match self.script_sig.instructions().next()? {
+            Ok(Instruction::Op(op @ OP_1..=OP_16)) => Some((op - OP_1 + 1) as u32),
+            // `OP_0` is an empty push, i.e. height 0
+            Ok(Instruction::Push(data)) if data.len() <= 5 => {
+                u32::try_from(decode_script_num(data)).ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// BIP68 relative lock time, or `None` if the disable flag (bit 31) is set.
+    pub fn relative_locktime(&self) -> Option<RelativeLockTime> {
+        const DISABLE_FLAG: u32 = 1 << 31;
+        const TYPE_FLAG: u32 = 1 << 22;
+        if self.sequence & DISABLE_FLAG != 0 {
+            return None;
+        }
+        let value = (self.sequence & 0xFFFF) as u16;
+        if self.sequence & TYPE_FLAG != 0 {
+            Some(RelativeLockTime::Time(value))
+        } else {
+            Some(RelativeLockTime::Blocks(value))
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn read_from<R: Read>(r: &mut R) -> Result<Self, BitcoinError> {
+        let previous_output = OutPoint::read_from(r)?;
+        let script_sig = Script::read_from(r)?;
+        let sequence = u32::from_le_bytes(read_array(r)?);
+        Ok(TransactionInput::new(previous_output, script_sig, sequence))
+    }
+}
+
+/// A transaction input whose scriptSig borrows from the serialized buffer.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TransactionInputRef<'a> {
+    pub previous_output: OutPoint,
+    pub script_sig: ScriptRef<'a>,
+    pub sequence: u32,
+}
+
+impl TransactionInputRef<'_> {
+    pub fn to_input(&self) -> TransactionInput {
+        TransactionInput::new(
+            self.previous_output.clone(),
+            self.script_sig.to_script(),
+            self.sequence,
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RelativeLockTime {
+    Blocks(u16),
+    /// In units of 512 seconds.
+    Time(u16),
+}
+
+// Witness stack: CompactSize item count, then each item CompactSize-prefixed.
+fn encode_witness<E: Encoder>(witness: &[Vec<u8>], e: &mut E) -> Result<usize, E::Error> {
+    let mut written = CompactSize::new(witness.len() as u64).encode(e)?;
+    for item in witness {
+        written += CompactSize::new(item.len() as u64).encode(e)?;
+        e.emit(item)?;
+        written += item.len();
+    }
+    Ok(written)
+}
+
+fn witness_from_bytes(bytes: &[u8]) -> Result<(Vec<Vec<u8>>, usize), BitcoinError> {
+    let (count, mut offset) = CompactSize::from_bytes(bytes)?;
+    let mut witness = Vec::new();
+    for _ in 0..count.value {
+        let (len, consumed) = CompactSize::from_bytes(slice_from(bytes, offset)?)?;
+        offset = advance(offset, consumed)?;
+        let len = len.as_usize_checked()?;
+        witness.push(slice_at(bytes, offset, len)?.to_vec());
+        offset = advance(offset, len)?;
+    }
+    Ok((witness, offset))
+}
+
+#[cfg(feature = "std")]
+fn read_witness<R: Read>(r: &mut R) -> Result<Vec<Vec<u8>>, BitcoinError> {
+    let count = CompactSize::read_from(r)?;
+    let mut witness = Vec::new();
+    for _ in 0..count.value {
+        let len = CompactSize::read_from(r)?;
+        witness.push(read_vec(r, len.value)?);
+    }
+    Ok(witness)
+}
+
+/// Total supply cap in satoshis (21 million BTC).
+pub const MAX_MONEY: u64 = 21_000_000 * Amount::SAT_PER_BTC;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct Amount(u64);
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+    pub const SAT_PER_BTC: u64 = 100_000_000;
+
+    /// Returns `None` if `sats` exceeds `MAX_MONEY`.
+    pub fn from_sat(sats: u64) -> Option<Self> {
+        (sats <= MAX_MONEY).then_some(Amount(sats))
+    }
+
+    pub fn to_sat(self) -> u64 {
+        self.0
+    }
+
+    /// Rounds to the nearest satoshi; rejects negative, non-finite, or
+    /// out-of-range values.
+    pub fn from_btc(btc: f64) -> Option<Self> {
+        let sats = btc * Self::SAT_PER_BTC as f64;
+        if !(0.0..=MAX_MONEY as f64).contains(&sats) {
+            return None;
+        }
+        // `f64::round` needs std; for non-negative values this is equivalent.
+        Self::from_sat((sats + 0.5) as u64)
+    }
+
+    pub fn to_btc(self) -> f64 {
+        self.0 as f64 / Self::SAT_PER_BTC as f64
+    }
+
+    pub fn checked_add(self, rhs: Amount) -> Option<Amount> {
+        self.0.checked_add(rhs.0).and_then(Self::from_sat)
+    }
+
+    pub fn checked_sub(self, rhs: Amount) -> Option<Amount> {
+        self.0.checked_sub(rhs.0).map(Amount)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TransactionOutput {
+    pub value: u64,
+    pub script_pubkey: Script,
+}
+
+impl TransactionOutput {
+    pub fn new(value: u64, script_pubkey: Script) -> Self {
+        Self {
+            value,
+            script_pubkey,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Serialize: value (8 bytes LE) + Script (with CompactSize)
+        encode_to_vec(|e| self.encode(e))
+    }
+
+    /// Appends the serialization to `buf` and returns the number of bytes written.
+    pub fn encode_into(&self, buf: &mut Vec<u8>) -> usize {
+        append_to_vec(buf, |e| self.encode(e))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<usize> {
+        self.encode(&mut IoEncoder(w))
+    }
+
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<usize, E::Error> {
+        e.emit(&self.value.to_le_bytes())?;
+        Ok(8 + self.script_pubkey.encode(e)?)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<(Self, &[u8]), BitcoinError> {
+        let (value, consumed) = Self::from_bytes(bytes)?;
+        Ok((value, &bytes[consumed..]))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        // Deserialize in order:
+        // - value (8 bytes)
+        // - Script (with CompactSize)
+        let value = array_at(bytes, 0)
+            .map(u64::from_le_bytes)
+            .map_err(|e| e.at(0, "value"))?;
+        let (script_pubkey, consumed) = slice_from(bytes, 8)
+            .and_then(Script::from_bytes)
+            .map_err(|e| e.at(8, "script_pubkey"))?;
+        Ok((TransactionOutput::new(value, script_pubkey), 8 + consumed))
+    }
+
+    /// The output value as a validated `Amount`, or `None` above `MAX_MONEY`.
+    pub fn amount(&self) -> Option<Amount> {
+        Amount::from_sat(self.value)
+    }
+
+    /// Smallest value that is not dust at `dust_relay_fee_per_kvb` (sat/kvB),
+    /// following bitcoind: the fee for this output plus the input spending it
+    /// (148 bytes, or 67 vbytes for witness programs), rounded up like
+    /// `CFeeRate::GetFee`. OP_RETURN outputs are never dust.
+    pub fn dust_threshold(&self, dust_relay_fee_per_kvb: u64) -> u64 {
+        if self.script_pubkey.is_op_return() {
+            return 0;
+        }
+        let spend_size = if self.script_pubkey.witness_program().is_some() {
+            32 + 4 + 1 + 107 / WITNESS_SCALE_FACTOR + 4
+        } else {
+            32 + 4 + 1 + 107 + 4
+        };
+        let size = (8 + self.script_pubkey.prefixed_len() + spend_size) as u64;
+        size.saturating_mul(dust_relay_fee_per_kvb).div_ceil(1000)
+    }
+
+    /// Whether the value is below `dust_threshold`.
+    pub fn is_dust(&self, dust_relay_fee_per_kvb: u64) -> bool {
+        self.value < self.dust_threshold(dust_relay_fee_per_kvb)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn read_from<R: Read>(r: &mut R) -> Result<Self, BitcoinError> {
+        let value = u64::from_le_bytes(read_array(r)?);
+        let script_pubkey = Script::read_from(r)?;
+        Ok(TransactionOutput::new(value, script_pubkey))
+    }
+}
+
+// Smallest possible encodings: outpoint (36) + empty script (1) + sequence (4),
+// and value (8) + empty script (1).
+const MIN_INPUT_SIZE: usize = 41;
+const MIN_OUTPUT_SIZE: usize = 9;
+
+// Declared counts are untrusted; never reserve more elements than the
+// remaining bytes could possibly encode.
+fn capped_capacity(count: u64, remaining: usize, min_size: usize) -> usize {
+    usize::try_from(count)
+        .unwrap_or(usize::MAX)
+        .min(remaining / min_size)
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BitcoinTransaction {
+    pub version: u32,
+    pub inputs: Vec<TransactionInput>,
+    pub outputs: Vec<TransactionOutput>,
+    pub lock_time: u32,
+}
+
+impl BitcoinTransaction {
+    pub fn new(
+        version: u32,
+        inputs: Vec<TransactionInput>,
+        outputs: Vec<TransactionOutput>,
+        lock_time: u32,
+    ) -> Self {
+        // TODO: Construct a transaction from parts
+        Self {
+            version,
+            inputs,
+            outputs,
+            lock_time,
+        }
+    }
+
+    pub fn has_witness(&self) -> bool {
+        self.inputs.iter().any(|input| !input.witness.is_empty())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_to_vec(|e| self.encode(e, self.has_witness()))
+    }
+
+    /// Appends the serialization to `buf` and returns the number of bytes
+    /// written, so one buffer can be cleared and reused across transactions.
+    pub fn encode_into(&self, buf: &mut Vec<u8>) -> usize {
+        append_to_vec(buf, |e| self.encode(e, self.has_witness()))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<usize> {
+        self.encode(&mut IoEncoder(w), self.has_witness())
+    }
+
+    /// Writes `txs` back to back, encoding each into one reused buffer so the
+    /// writer sees a single `write_all` per transaction. Returns bytes written.
+    #[cfg(feature = "std")]
+    pub fn write_all<W: Write>(txs: &[BitcoinTransaction], w: &mut W) -> io::Result<usize> {
+        let mut buf = Vec::new();
+        let mut written = 0;
+        for tx in txs {
+            buf.clear();
+            written += tx.encode_into(&mut buf);
+            w.write_all(&buf)?;
+        }
+        Ok(written)
+    }
+
+    /// The legacy serialization: no marker, flag or witnesses. This is what
+    /// the txid commits to.
+    pub fn to_bytes_without_witness(&self) -> Vec<u8> {
+        encode_to_vec(|e| self.encode(e, false))
+    }
+
+    fn encode<E: Encoder>(&self, e: &mut E, include_witness: bool) -> Result<usize, E::Error> {
+        // TODO: Format:
+        // - version (4 bytes LE)
+        // - [segwit only] marker 0x00 + flag 0x01
+        // - CompactSize (number of inputs)
+        // - each input serialized
+        // - CompactSize (number of outputs)
+        // - each output serialized
+        // - [segwit only] witness stack for each input
+        // - lock_time (4 bytes LE)
+        e.emit(&self.version.to_le_bytes())?;
+        let mut written = 4;
+        if include_witness {
+            e.emit(&[0x00, 0x01])?;
+            written += 2;
+        }
+        written += CompactSize::new(self.inputs.len() as u64).encode(e)?;
+        for input in &self.inputs {
+            written += input.encode(e)?;
+        }
+        written += CompactSize::new(self.outputs.len() as u64).encode(e)?;
+        for output in &self.outputs {
+            written += output.encode(e)?;
+        }
+        if include_witness {
+            for input in &self.inputs {
+                written += encode_witness(&input.witness, e)?;
+            }
+        }
+        e.emit(&self.lock_time.to_le_bytes())?;
+        Ok(written + 4)
+    }
+
+    /// Parses one transaction and returns it with the unconsumed rest of
+    /// `bytes`, for reading back-to-back transactions from one buffer.
+    pub fn parse(bytes: &[u8]) -> Result<(Self, &[u8]), BitcoinError> {
+        let (value, consumed) = Self::from_bytes(bytes)?;
+        Ok((value, &bytes[consumed..]))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let mut tx = BitcoinTransaction::new(0, Vec::new(), Vec::new(), 0);
+        let mut consumed = 0;
+        Self::decode(bytes, &ParseLimits::NONE, &mut tx, &mut consumed)?;
+        Ok((tx, consumed))
+    }
+
+    /// Like `from_bytes`, but counts and script lengths above `limits` are
+    /// `InvalidFormat`. Counts are checked before any of the items are read.
+    pub fn from_bytes_with_limits(
+        bytes: &[u8],
+        limits: &ParseLimits,
+    ) -> Result<(Self, usize), BitcoinError> {
+        let mut tx = BitcoinTransaction::new(0, Vec::new(), Vec::new(), 0);
+        let mut consumed = 0;
+        Self::decode(bytes, limits, &mut tx, &mut consumed)?;
+        Ok((tx, consumed))
+    }
+
+    /// Best-effort parse for recovering data from corrupt dumps. Returns the
+    /// transaction as far as it decoded (`None` if not even the version did),
+    /// the offset just past the last fully decoded field, and the error that
+    /// stopped decoding, if any.
+    pub fn from_bytes_partial(
+        bytes: &[u8],
+    ) -> (Option<BitcoinTransaction>, usize, Option<BitcoinError>) {
+        let mut tx = BitcoinTransaction::new(0, Vec::new(), Vec::new(), 0);
+        let mut consumed = 0;
+        match Self::decode(bytes, &ParseLimits::NONE, &mut tx, &mut consumed) {
+            Ok(()) => (Some(tx), consumed, None),
+            Err(e) => ((consumed > 0).then_some(tx), consumed, Some(e)),
+        }
+    }
+
+    // Decodes into `tx` field by field, advancing `consumed` past each one
+    // that is complete so a failure leaves the partial result behind.
+    fn decode(
+        bytes: &[u8],
+        limits: &ParseLimits,
+        tx: &mut BitcoinTransaction,
+        consumed: &mut usize,
+    ) -> Result<(), BitcoinError> {
+        // TODO: Read version, CompactSize for input count
+        // Parse inputs one by one
+        // Read CompactSize for output count and parse outputs
+        // If the segwit marker was present, read one witness stack per input
+        // Read final 4 bytes for lock_time
+        // Errors carry the absolute offset and field where decoding failed.
+        tx.version = array_at(bytes, 0)
+            .map(u32::from_le_bytes)
+            .map_err(|e| e.at(0, "version"))?;
+        *consumed = 4;
+        // A 0x00 after the version is the segwit marker when followed by the
+        // 0x01 flag. As in bitcoind, `00 00` is an empty transaction (zero
+        // inputs, zero outputs); any other flag is invalid.
+        let (marker, flag) = (bytes.get(4).copied(), bytes.get(5).copied());
+        let segwit = marker == Some(0x00) && flag == Some(0x01);
+        if marker == Some(0x00) && flag.is_some_and(|flag| flag > 0x01) {
+            return Err(BitcoinError::InvalidFormat.at(5, "segwit flag"));
+        }
+        let mut offset = if segwit { 6 } else { 4 };
+        let (input_count, size) = slice_from(bytes, offset)
+            .and_then(CompactSize::from_bytes)
+            .map_err(|e| e.at(offset, "input count"))?;
+        if input_count.value > limits.max_inputs as u64 {
+            return Err(BitcoinError::InvalidFormat.at(offset, "input count"));
+        }
+        // Fail fast when the remaining bytes cannot hold that many minimal inputs
+        let min_inputs_len = input_count.value.saturating_mul(MIN_INPUT_SIZE as u64);
+        if min_inputs_len > bytes.len().saturating_sub(offset.saturating_add(size)) as u64 {
+            return Err(BitcoinError::InsufficientBytes.at(offset, "input count"));
+        }
+        offset = advance(offset, size).map_err(|e| e.at(offset, "input count"))?;
+        *consumed = offset;
+        tx.inputs.reserve(capped_capacity(
+            input_count.value,
+            bytes.len().saturating_sub(offset),
+            MIN_INPUT_SIZE,
+        ));
+        let input_count = input_count
+            .as_usize_checked()
+            .map_err(|e| e.at(offset, "input count"))?;
+        let inputs_start = offset;
+        let input_bytes = slice_from(bytes, offset).map_err(|e| e.at(offset, "input"))?;
+        for item in TransactionInput::parse_many(input_bytes, input_count) {
+            let (input, input_size) = item.map_err(|e| e.at(inputs_start, "input"))?;
+            if input.script_sig.len() > limits.max_script_len {
+                return Err(BitcoinError::InvalidFormat.at(offset, "input"));
+            }
+            tx.inputs.push(input);
+            offset = advance(offset, input_size).map_err(|e| e.at(offset, "input"))?;
+            *consumed = offset;
+        }
+        let (output_count, size) = slice_from(bytes, offset)
+            .and_then(CompactSize::from_bytes)
+            .map_err(|e| e.at(offset, "output count"))?;
+        if output_count.value > limits.max_outputs as u64 {
+            return Err(BitcoinError::InvalidFormat.at(offset, "output count"));
+        }
+        offset = advance(offset, size).map_err(|e| e.at(offset, "output count"))?;
+        *consumed = offset;
+        tx.outputs.reserve(capped_capacity(
+            output_count.value,
+            bytes.len().saturating_sub(offset),
+            MIN_OUTPUT_SIZE,
+        ));
+        for _ in 0..output_count.value {
+            let (output, output_size) = slice_from(bytes, offset)
+                .and_then(TransactionOutput::from_bytes)
+                .map_err(|e| e.at(offset, "output"))?;
+            if output.script_pubkey.len() > limits.max_script_len {
+                return Err(BitcoinError::InvalidFormat.at(offset, "output"));
+            }
+            tx.outputs.push(output);
+            offset = advance(offset, output_size).map_err(|e| e.at(offset, "output"))?;
+            *consumed = offset;
+        }
+        if segwit {
+            let witness_start = offset;
+            for input in tx.inputs.iter_mut() {
+                let (witness, witness_size) = slice_from(bytes, offset)
+                    .and_then(witness_from_bytes)
+                    .map_err(|e| e.at(offset, "witness"))?;
+                input.witness = witness;
+                offset = advance(offset, witness_size).map_err(|e| e.at(offset, "witness"))?;
+                *consumed = offset;
+            }
+            // A marker with no actual witness data would not re-serialize identically
+            if tx.inputs.iter().all(|input| input.witness.is_empty()) {
+                return Err(BitcoinError::InvalidFormat.at(witness_start, "witness"));
+            }
+        }
+        tx.lock_time = array_at(bytes, offset)
+            .map(u32::from_le_bytes)
+            .map_err(|e| e.at(offset, "lock_time"))?;
+        *consumed = advance(offset, 4).map_err(|e| e.at(offset, "lock_time"))?;
+        Ok(())
+    }
+
+    /// Like `from_bytes`, but the slice must hold exactly one transaction.
+    pub fn from_bytes_strict(bytes: &[u8]) -> Result<Self, BitcoinError> {
+        let (tx, consumed) = Self::from_bytes(bytes)?;
+        if consumed != bytes.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(tx)
+    }
+
+    /// Whether `bytes` is exactly one transaction that re-serializes to the
+    /// same bytes: no trailing data or non-minimal encodings.
+    pub fn is_canonical(bytes: &[u8]) -> bool {
+        Self::from_bytes_strict(bytes).is_ok_and(|tx| tx.to_bytes() == bytes)
+    }
+
+    /// Decodes a dump of transactions, each preceded by its length as a 4-byte
+    /// little-endian integer. Errors report the offset within `bytes`.
+    pub fn decode_all(bytes: &[u8]) -> Result<Vec<BitcoinTransaction>, BitcoinError> {
+        let mut txs = Vec::new();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let len = array_at(bytes, offset)
+                .map(u32::from_le_bytes)
+                .map_err(|e| e.at(offset, "length prefix"))?;
+            offset = advance(offset, 4).map_err(|e| e.at(offset, "length prefix"))?;
+            let len = usize::try_from(len)
+                .map_err(|_| BitcoinError::InsufficientBytes.at(offset, "transaction"))?;
+            let tx = slice_at(bytes, offset, len)
+                .and_then(Self::from_bytes_strict)
+                .map_err(|e| e.at(offset, "transaction"))?;
+            txs.push(tx);
+            offset = advance(offset, len).map_err(|e| e.at(offset, "transaction"))?;
+        }
+        Ok(txs)
+    }
+
+    /// Parses a raw transaction hex string such as `getrawtransaction` returns.
+    /// ASCII whitespace (spaces, newlines from pasted logs) is ignored.
+    pub fn from_hex(s: &str) -> Result<Self, BitcoinError> {
+        let digits: Vec<u8> = s.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+        let bytes = hex::decode(digits).map_err(|_| BitcoinError::InvalidFormat)?;
+        Self::from_bytes_strict(&bytes)
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.to_bytes())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn read_from<R: Read>(r: &mut R) -> Result<Self, BitcoinError> {
+        let version = u32::from_le_bytes(read_array(r)?);
+        // A zero input count may instead be the segwit marker. A 0x00 flag is
+        // really a zero output count; any flag other than 0x00/0x01 is invalid.
+        let [mut prefix] = read_array(r)?;
+        let mut segwit = false;
+        let mut output_prefix = None;
+        if prefix == 0x00 {
+            let [flag] = read_array(r)?;
+            match flag {
+                0x00 => output_prefix = Some(flag),
+                0x01 => {
+                    segwit = true;
+                    [prefix] = read_array(r)?;
+                }
+                _ => return Err(BitcoinError::InvalidFormat),
+            }
+        }
+        let input_count = CompactSize::read_with_prefix(prefix, r)?;
+        let mut inputs = Vec::new();
+        for _ in 0..input_count.value {
+            inputs.push(TransactionInput::read_from(r)?);
+        }
+        let output_count = match output_prefix {
+            Some(prefix) => CompactSize::read_with_prefix(prefix, r)?,
+            None => CompactSize::read_from(r)?,
+        };
+        let mut outputs = Vec::new();
+        for _ in 0..output_count.value {
+            outputs.push(TransactionOutput::read_from(r)?);
+        }
+        if segwit {
+            for input in inputs.iter_mut() {
+                input.witness = read_witness(r)?;
+            }
+            if inputs.iter().all(|input| input.witness.is_empty()) {
+                return Err(BitcoinError::InvalidFormat);
+            }
+        }
+        let lock_time = u32::from_le_bytes(read_array(r)?);
+        Ok(BitcoinTransaction::new(version, inputs, outputs, lock_time))
+    }
+
+    /// The version as the signed integer consensus code historically used;
+    /// the stored `u32` keeps the raw 4 bytes either way.
+    pub fn version_signed(&self) -> i32 {
+        self.version as i32
+    }
+
+    /// Whether relay policy accepts this version (1 or 2). The raw `version`
+    /// is kept as-is so nonstandard transactions still round-trip.
+    pub fn is_standard_version(&self) -> bool {
+        matches!(self.version, 1 | 2)
+    }
+
+    /// Whether relay policy accepts this size: at most `MAX_STANDARD_TX_WEIGHT`.
+    pub fn is_standard_size(&self) -> bool {
+        self.weight_units() <= MAX_STANDARD_TX_WEIGHT
+    }
+
+    /// A copy with input `index`'s scriptSig replaced; `InvalidFormat` if
+    /// there is no such input.
+    pub fn with_script_sig(
+        &self,
+        index: usize,
+        script: Script,
+    ) -> Result<BitcoinTransaction, BitcoinError> {
+        let mut tx = self.clone();
+        tx.inputs
+            .get_mut(index)
+            .ok_or(BitcoinError::InvalidFormat)?
+            .script_sig = script;
+        Ok(tx)
+    }
+
+    /// Sorts inputs by previous txid (display byte order) then vout, and
+    /// outputs by value then scriptPubKey bytes, as BIP69 specifies. Witness
+    /// stacks move with their inputs.
+    pub fn sort_bip69(&mut self) {
+        self.inputs.sort_by(|a, b| {
+            let (a, b) = (&a.previous_output, &b.previous_output);
+            (a.txid.to_be_bytes(), a.vout).cmp(&(b.txid.to_be_bytes(), b.vout))
+        });
+        self.outputs.sort_by(|a, b| {
+            (a.value, &a.script_pubkey.bytes).cmp(&(b.value, &b.script_pubkey.bytes))
+        });
+    }
+
+    /// A copy with every witness stack cleared.
+    pub fn strip_witness(&self) -> BitcoinTransaction {
+        let mut tx = self.clone();
+        for input in &mut tx.inputs {
+            input.witness.clear();
+        }
+        tx
+    }
+
+    /// A copy with every scriptSig emptied.
+    pub fn with_all_script_sigs_cleared(&self) -> BitcoinTransaction {
+        let mut tx = self.clone();
+        for input in &mut tx.inputs {
+            input.script_sig = Script::new(Vec::new());
+        }
+        tx
+    }
+
+    /// Gives every input the same sequence, e.g. `0xFFFFFFFF` to finalize
+    /// or a uniform relative lock time.
+    pub fn set_all_sequences(&mut self, sequence: u32) {
+        for input in &mut self.inputs {
+            input.sequence = sequence;
+        }
+    }
+
+    pub fn input_mut(&mut self, index: usize) -> Option<&mut TransactionInput> {
+        self.inputs.get_mut(index)
+    }
+
+    pub fn output_mut(&mut self, index: usize) -> Option<&mut TransactionOutput> {
+        self.outputs.get_mut(index)
+    }
+
+    /// The outpoint spent by each input, in input order.
+    pub fn prevouts(&self) -> Vec<&OutPoint> {
+        self.inputs
+            .iter()
+            .map(|input| &input.previous_output)
+            .collect()
+    }
+
+    pub fn is_coinbase(&self) -> bool {
+        self.inputs.len() == 1 && self.inputs[0].previous_output.is_null()
+    }
+
+    /// A version 2 coinbase paying `reward` to `script_pubkey`. The scriptSig
+    /// is the BIP34 height followed by `OP_0`, as bitcoind's block template
+    /// builds it, which also keeps it at the 2-byte consensus minimum.
+    pub fn coinbase(height: u32, script_pubkey: Script, reward: u64) -> BitcoinTransaction {
+        let script_sig = Script::builder()
+            .push_int(height as i64)
+            .push_opcode(OP_0)
+            .into_script();
+        let input = TransactionInput::new(OutPoint::new([0; 32], u32::MAX), script_sig, u32::MAX);
+        let output = TransactionOutput::new(reward, script_pubkey);
+        BitcoinTransaction::new(2, vec![input], vec![output], 0)
+    }
+
+    /// Whether both transactions spend at least one common outpoint, i.e.
+    /// one would replace the other in a mempool.
+    pub fn conflicts_with(&self, other: &BitcoinTransaction) -> bool {
+        let spent: BTreeSet<&OutPoint> = self.prevouts().into_iter().collect();
+        other
+            .inputs
+            .iter()
+            .any(|input| spent.contains(&input.previous_output))
+    }
+
+    /// Sigops in every scriptSig and scriptPubKey, counted inaccurately as
+    /// consensus does for the legacy block limit.
+    pub fn legacy_sigops(&self) -> usize {
+        let script_sigs = self.inputs.iter().map(|input| &input.script_sig);
+        let script_pubkeys = self.outputs.iter().map(|output| &output.script_pubkey);
+        script_sigs
+            .chain(script_pubkeys)
+            .map(|script| script.sigop_count(false))
+            .sum()
+    }
+
+    /// BIP125 opt-in: any input signals replaceability.
+    pub fn signals_rbf(&self) -> bool {
+        self.inputs.iter().any(TransactionInput::enables_rbf)
+    }
+
+    pub fn parsed_lock_time(&self) -> LockTime {
+        LockTime::from_consensus(self.lock_time)
+    }
+
+    /// The lock time is only enforced if it is non-zero and at least one
+    /// input has a non-final sequence.
+    pub fn is_lock_time_enabled(&self) -> bool {
+        self.lock_time != 0 && self.inputs.iter().any(|input| !input.is_final())
+    }
+
+    /// Consensus finality (bitcoind's `IsFinalTx`) for inclusion in a block at
+    /// `height` whose median-time-past is `mtp`: the lock time must be below
+    /// the height or time it is measured in, unless it isn't enabled at all.
+    pub fn is_final_at(&self, height: u32, mtp: u32) -> bool {
+        if !self.is_lock_time_enabled() {
+            return true;
+        }
+        match self.parsed_lock_time() {
+            LockTime::Height(lock_height) => lock_height < height,
+            LockTime::Time(lock_time) => lock_time < mtp,
+        }
+    }
+
+    /// Sum of all output values, saturating at `u64::MAX` on overflow.
+    pub fn total_output_value(&self) -> u64 {
+        self.outputs
+            .iter()
+            .try_fold(0u64, |sum, output| sum.checked_add(output.value))
+            .unwrap_or(u64::MAX)
+    }
+
+    /// Fee paid given the values of the outputs being spent, one per input in
+    /// order. Fails if a value is missing, a sum overflows, or the outputs
+    /// exceed the inputs.
+    pub fn fee(&self, input_values: &[u64]) -> Result<u64, BitcoinError> {
+        if input_values.len() != self.inputs.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let total_in = input_values
+            .iter()
+            .try_fold(0u64, |sum, value| sum.checked_add(*value))
+            .ok_or(BitcoinError::InvalidFormat)?;
+        // Not `total_output_value`: its saturated sum would hide an overflow
+        let total_out = self
+            .outputs
+            .iter()
+            .try_fold(0u64, |sum, output| sum.checked_add(output.value))
+            .ok_or(BitcoinError::InvalidFormat)?;
+        total_in
+            .checked_sub(total_out)
+            .ok_or(BitcoinError::InvalidFormat)
+    }
+
+    /// Effective fee rate in sat/vB; fails under the same conditions as `fee`.
+    pub fn fee_rate(&self, input_values: &[u64]) -> Result<f64, BitcoinError> {
+        let fee = self.fee(input_values)?;
+        Ok(fee as f64 / self.vsize() as f64)
+    }
+
+    /// Index of the `bucket_size`-wide sat/vB bucket holding `fee_rate`, i.e.
+    /// `floor(fee_rate / bucket_size)`. A non-positive or non-finite
+    /// `bucket_size` is `InvalidFormat`.
+    pub fn fee_rate_bucket(
+        &self,
+        input_values: &[u64],
+        bucket_size: f64,
+    ) -> Result<u32, BitcoinError> {
+        if !(bucket_size.is_finite() && bucket_size > 0.0) {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        // Both operands are non-negative, so truncating is flooring
+        Ok((self.fee_rate(input_values)? / bucket_size) as u32)
+    }
+
+    /// Serialized size without marker, flag, or witness data.
+    pub fn base_size(&self) -> usize {
+        encoded_size(|e| self.encode(e, false))
+    }
+
+    /// Serialized size including witness data, i.e. `to_bytes().len()`.
+    pub fn total_size(&self) -> usize {
+        encoded_size(|e| self.encode(e, self.has_witness()))
+    }
+
+    /// Byte range of each input within `to_bytes()`.
+    pub fn input_offsets(&self) -> Vec<Range<usize>> {
+        let mut start = if self.has_witness() { 6 } else { 4 };
+        start += CompactSize::new(self.inputs.len() as u64).encoded_len();
+        self.inputs
+            .iter()
+            .map(|input| {
+                let end = start + encoded_size(|e| input.encode(e));
+                let range = start..end;
+                start = end;
+                range
+            })
+            .collect()
+    }
+
+    /// Byte range of each output within `to_bytes()`.
+    pub fn output_offsets(&self) -> Vec<Range<usize>> {
+        // With no inputs, outputs follow the version and the one-byte zero input count
+        let inputs_end = self.input_offsets().last().map_or(5, |last| last.end);
+        let mut start = inputs_end + CompactSize::new(self.outputs.len() as u64).encoded_len();
+        self.outputs
+            .iter()
+            .map(|output| {
+                let end = start + encoded_size(|e| output.encode(e));
+                let range = start..end;
+                start = end;
+                range
+            })
+            .collect()
+    }
+
+    /// Length of `to_bytes()`, without building the buffer. Same as `total_size`.
+    pub fn serialized_size(&self) -> usize {
+        self.total_size()
+    }
+
+    /// Virtual size once signed, given the weight each input's signature data
+    /// will add (scriptSig bytes count 4 WU, witness bytes 1 WU). The 2 WU
+    /// segwit marker and flag are counted whenever any input adds weight, so
+    /// legacy-only estimates may come out up to one vbyte high.
+    pub fn estimated_vsize(&self, input_weights: &[usize]) -> Result<usize, BitcoinError> {
+        if input_weights.len() != self.inputs.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let added: usize = input_weights.iter().sum();
+        let marker_and_flag = if added > 0 { 2 } else { 0 };
+        let weight = self.base_size() * WITNESS_SCALE_FACTOR + marker_and_flag + added;
+        Ok(weight.div_ceil(WITNESS_SCALE_FACTOR))
+    }
+
+    /// BIP141 weight: witness bytes count once, everything else four times.
+    pub fn weight(&self) -> usize {
+        self.base_size() * (WITNESS_SCALE_FACTOR - 1) + self.total_size()
+    }
+
+    /// Same as `weight`, named for use alongside fee math in weight units.
+    pub fn weight_units(&self) -> usize {
+        self.weight()
+    }
+
+    /// Weight divided by `WITNESS_SCALE_FACTOR`, rounded up.
+    pub fn vsize(&self) -> usize {
+        self.weight_units().div_ceil(WITNESS_SCALE_FACTOR)
+    }
+
+    /// Double-SHA256 of the serialized transaction without witness data, in
+    /// internal byte order. Reverse the bytes to get the txid as shown by block explorers.
+    pub fn txid(&self) -> [u8; 32] {
+        sha256d(&self.to_bytes_without_witness())
+    }
+
+    /// Double-SHA256 of the full serialization including marker, flag and
+    /// witnesses (BIP141). Equal to `txid()` when there is no witness data.
+    pub fn wtxid(&self) -> [u8; 32] {
+        sha256d(&self.to_bytes())
+    }
+
+    /// `to_bytes()` and `txid()` from a single serialization: the txid hashes
+    /// the bytes around the marker, flag and witnesses instead of encoding
+    /// the transaction a second time.
+    pub fn serialize_with_txid(&self) -> (Vec<u8>, [u8; 32]) {
+        let bytes = self.to_bytes();
+        if !self.has_witness() {
+            let txid = sha256d(&bytes);
+            return (bytes, txid);
+        }
+        let witness_len: usize = self
+            .inputs
+            .iter()
+            .map(|input| encoded_size(|e| encode_witness(&input.witness, e)))
+            .sum();
+        let witness_start = bytes.len() - 4 - witness_len;
+        let txid = sha256d_concat(&[
+            &bytes[..4],
+            &bytes[6..witness_start],
+            &bytes[bytes.len() - 4..],
+        ]);
+        (bytes, txid)
+    }
+
+    /// Whether both have the same txid, compared on the witness-stripped
+    /// serialization without hashing.
+    pub fn same_txid(&self, other: &BitcoinTransaction) -> bool {
+        self.to_bytes_without_witness() == other.to_bytes_without_witness()
+    }
+
+    /// BIP143 signature hash for spending a segwit v0 output at `input_index`.
+    /// `script_code` is the script being satisfied (for P2WPKH, the implied
+    /// P2PKH script) and `value` the amount of the output being spent.
+    pub fn signature_hash_segwit(
+        &self,
+        input_index: usize,
+        script_code: &Script,
+        value: u64,
+        sighash_type: SighashType,
+    ) -> Result<[u8; 32], BitcoinError> {
+        let input = self
+            .inputs
+            .get(input_index)
+            .ok_or(BitcoinError::InvalidFormat)?;
+        let sighash_type = sighash_type.to_u32();
+        let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY != 0;
+        let base_type = sighash_type & 0x1f;
+
+        let hash_prevouts = if anyone_can_pay {
+            [0u8; 32]
+        } else {
+            sha256d(&encode_to_vec(|e| {
+                for input in &self.inputs {
+                    input.previous_output.encode(e)?;
+                }
+                Ok(0)
+            }))
+        };
+        let hash_sequence =
+            if anyone_can_pay || base_type == SIGHASH_NONE || base_type == SIGHASH_SINGLE {
+                [0u8; 32]
+            } else {
+                sha256d(&encode_to_vec(|e| {
+                    for input in &self.inputs {
+                        e.emit(&input.sequence.to_le_bytes())?;
+                    }
+                    Ok(0)
+                }))
+            };
+        let hash_outputs = if base_type != SIGHASH_NONE && base_type != SIGHASH_SINGLE {
+            sha256d(&encode_to_vec(|e| {
+                for output in &self.outputs {
+                    output.encode(e)?;
+                }
+                Ok(0)
+            }))
+        } else if base_type == SIGHASH_SINGLE && input_index < self.outputs.len() {
+            sha256d(&self.outputs[input_index].to_bytes())
+        } else {
+            [0u8; 32]
+        };
+
+        let preimage = encode_to_vec(|e| {
+            e.emit(&self.version.to_le_bytes())?;
+            e.emit(&hash_prevouts)?;
+            e.emit(&hash_sequence)?;
+            input.previous_output.encode(e)?;
+            script_code.encode(e)?;
+            e.emit(&value.to_le_bytes())?;
+            e.emit(&input.sequence.to_le_bytes())?;
+            e.emit(&hash_outputs)?;
+            e.emit(&self.lock_time.to_le_bytes())?;
+            e.emit(&sighash_type.to_le_bytes())?;
+            Ok(0)
+        });
+        Ok(sha256d(&preimage))
+    }
+
+    /// Pre-segwit signature hash for `input_index`, with `script_code` standing
+    /// in for that input's scriptSig. SIGHASH_SINGLE without a matching output
+    /// returns the value 1 (`01 00 .. 00`), reproducing bitcoind's behaviour.
+    pub fn signature_hash_legacy(
+        &self,
+        input_index: usize,
+        script_code: &Script,
+        sighash_type: SighashType,
+    ) -> Result<[u8; 32], BitcoinError> {
+        if input_index >= self.inputs.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let sighash_type = sighash_type.to_u32();
+        let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY != 0;
+        let base_type = sighash_type & 0x1f;
+        if base_type == SIGHASH_SINGLE && input_index >= self.outputs.len() {
+            let mut one = [0u8; 32];
+            one[0] = 0x01;
+            return Ok(one);
+        }
+
+        let script_code = Script::new(script_code.without_codeseparators());
+        let empty = Script::new(Vec::new());
+        let preimage = encode_to_vec(|e| {
+            e.emit(&self.version.to_le_bytes())?;
+            let signed_inputs = if anyone_can_pay {
+                input_index..input_index + 1
+            } else {
+                0..self.inputs.len()
+            };
+            CompactSize::new(signed_inputs.len() as u64).encode(e)?;
+            for i in signed_inputs {
+                let input = &self.inputs[i];
+                let is_signed = i == input_index;
+                let sequence =
+                    if !is_signed && (base_type == SIGHASH_NONE || base_type == SIGHASH_SINGLE) {
+                        0
+                    } else {
+                        input.sequence
+                    };
+                input.previous_output.encode(e)?;
+                if is_signed {
+                    script_code.encode(e)?;
+                } else {
+                    empty.encode(e)?;
+                }
+                e.emit(&sequence.to_le_bytes())?;
+            }
+            match base_type {
+                SIGHASH_NONE => {
+                    CompactSize::new(0).encode(e)?;
+                }
+                SIGHASH_SINGLE => {
+                    // Earlier outputs are blanked to value -1 with an empty script
+                    CompactSize::new(input_index as u64 + 1).encode(e)?;
+                    for _ in 0..input_index {
+                        TransactionOutput::new(u64::MAX, empty.clone()).encode(e)?;
+                    }
+                    self.outputs[input_index].encode(e)?;
+                }
+                _ => {
+                    CompactSize::new(self.outputs.len() as u64).encode(e)?;
+                    for output in &self.outputs {
+                        output.encode(e)?;
+                    }
+                }
+            }
+            e.emit(&self.lock_time.to_le_bytes())?;
+            e.emit(&sighash_type.to_le_bytes())?;
+            Ok(0)
+        });
+        Ok(sha256d(&preimage))
+    }
+}
+
+pub const SIGHASH_ALL: u32 = 0x01;
+pub const SIGHASH_NONE: u32 = 0x02;
+pub const SIGHASH_SINGLE: u32 = 0x03;
+pub const SIGHASH_ANYONECANPAY: u32 = 0x80;
+
+/// The sighash flag combinations bitcoind treats as standard.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SighashType {
+    All,
+    None,
+    Single,
+    AllPlusAnyoneCanPay,
+    NonePlusAnyoneCanPay,
+    SinglePlusAnyoneCanPay,
+}
+
+impl SighashType {
+    /// Parses a raw flag; `InvalidFormat` for an unknown base type or any
+    /// bit set outside the base type and `SIGHASH_ANYONECANPAY`.
+    pub fn from_u32(value: u32) -> Result<Self, BitcoinError> {
+        const ALL_ACP: u32 = SIGHASH_ALL | SIGHASH_ANYONECANPAY;
+        const NONE_ACP: u32 = SIGHASH_NONE | SIGHASH_ANYONECANPAY;
+        const SINGLE_ACP: u32 = SIGHASH_SINGLE | SIGHASH_ANYONECANPAY;
+        match value {
+            SIGHASH_ALL => Ok(SighashType::All),
+            SIGHASH_NONE => Ok(SighashType::None),
+            SIGHASH_SINGLE => Ok(SighashType::Single),
+            ALL_ACP => Ok(SighashType::AllPlusAnyoneCanPay),
+            NONE_ACP => Ok(SighashType::NonePlusAnyoneCanPay),
+            SINGLE_ACP => Ok(SighashType::SinglePlusAnyoneCanPay),
+            _ => Err(BitcoinError::InvalidFormat),
+        }
+    }
+
+    pub fn to_u32(self) -> u32 {
+        match self {
+            SighashType::All => SIGHASH_ALL,
+            SighashType::None => SIGHASH_NONE,
+            SighashType::Single => SIGHASH_SINGLE,
+            SighashType::AllPlusAnyoneCanPay => SIGHASH_ALL | SIGHASH_ANYONECANPAY,
+            SighashType::NonePlusAnyoneCanPay => SIGHASH_NONE | SIGHASH_ANYONECANPAY,
+            SighashType::SinglePlusAnyoneCanPay => SIGHASH_SINGLE | SIGHASH_ANYONECANPAY,
+        }
+    }
+}
+
+/// bitcoind's default `-dustrelayfee`, in sat/kvB.
+pub const DUST_RELAY_TX_FEE: u64 = 3_000;
+
+/// Dust threshold of a P2WPKH output at `DUST_RELAY_TX_FEE`.
+pub const P2WPKH_DUST_THRESHOLD: u64 = 294;
+
+/// Weight units per non-witness byte (BIP141); one vbyte is this many WU.
+pub const WITNESS_SCALE_FACTOR: usize = 4;
+
+/// Largest transaction weight bitcoind relays by default.
+pub const MAX_STANDARD_TX_WEIGHT: usize = 400_000;
+
+/// Upper bounds enforced by `BitcoinTransaction::from_bytes_with_limits`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ParseLimits {
+    pub max_inputs: usize,
+    pub max_outputs: usize,
+    /// Applies to both scriptSigs and scriptPubKeys.
+    pub max_script_len: usize,
+}
+
+impl ParseLimits {
+    // What `from_bytes` accepts.
+    const NONE: ParseLimits = ParseLimits {
+        max_inputs: usize::MAX,
+        max_outputs: usize::MAX,
+        max_script_len: usize::MAX,
+    };
+
+    /// The most a transaction can hold and still fit in a 4,000,000 WU block:
+    /// 1,000,000 non-witness bytes of minimal inputs or outputs, or of a
+    /// single script. Consensus puts no tighter bound on scriptPubKeys.
+    pub fn consensus() -> Self {
+        const MAX_BASE_SIZE: usize = 4_000_000 / WITNESS_SCALE_FACTOR;
+        ParseLimits {
+            max_inputs: MAX_BASE_SIZE / MIN_INPUT_SIZE,
+            max_outputs: MAX_BASE_SIZE / MIN_OUTPUT_SIZE,
+            max_script_len: MAX_BASE_SIZE,
+        }
+    }
+
+    /// Relay policy: what fits in `MAX_STANDARD_TX_WEIGHT`, with scripts no
+    /// longer than bitcoind's 1650-byte standard scriptSig limit.
+    pub fn standard() -> Self {
+        const MAX_BASE_SIZE: usize = MAX_STANDARD_TX_WEIGHT / WITNESS_SCALE_FACTOR;
+        ParseLimits {
+            max_inputs: MAX_BASE_SIZE / MIN_INPUT_SIZE,
+            max_outputs: MAX_BASE_SIZE / MIN_OUTPUT_SIZE,
+            max_script_len: 1_650,
+        }
+    }
+}
+
+/// `lock_time` values below this are block heights, at or above are Unix timestamps.
+pub const LOCK_TIME_THRESHOLD: u32 = 500_000_000;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LockTime {
+    Height(u32),
+    Time(u32),
+}
+
+impl LockTime {
+    pub fn from_consensus(value: u32) -> Self {
+        if value < LOCK_TIME_THRESHOLD {
+            LockTime::Height(value)
+        } else {
+            LockTime::Time(value)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TransactionBuilder {
+    version: u32,
+    inputs: Vec<TransactionInput>,
+    outputs: Vec<TransactionOutput>,
+    lock_time: u32,
+}
+
+impl TransactionBuilder {
+    pub fn new() -> Self {
+        Self {
+            version: 2,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            lock_time: 0,
+        }
+    }
+
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn add_input(
+        mut self,
+        previous_output: OutPoint,
+        script_sig: Script,
+        sequence: u32,
+    ) -> Self {
+        self.inputs
+            .push(TransactionInput::new(previous_output, script_sig, sequence));
+        self
+    }
+
+    pub fn add_output(mut self, value: u64, script_pubkey: Script) -> Self {
+        self.outputs
+            .push(TransactionOutput::new(value, script_pubkey));
+        self
+    }
+
+    pub fn lock_time(mut self, lock_time: u32) -> Self {
+        self.lock_time = lock_time;
+        self
+    }
+
+    pub fn build(self) -> BitcoinTransaction {
+        BitcoinTransaction::new(self.version, self.inputs, self.outputs, self.lock_time)
+    }
+}
+
+impl Default for TransactionBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The 80-byte block header. Hashes are stored in internal byte order.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BlockHeader {
+    pub version: i32,
+    pub prev_blockhash: [u8; 32],
+    pub merkle_root: [u8; 32],
+    pub time: u32,
+    pub bits: u32,
+    pub nonce: u32,
+}
+
+impl BlockHeader {
+    pub const SIZE: usize = 80;
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_to_vec(|e| self.encode(e))
+    }
+
+    /// Appends the serialization to `buf` and returns the number of bytes written.
+    pub fn encode_into(&self, buf: &mut Vec<u8>) -> usize {
+        append_to_vec(buf, |e| self.encode(e))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<usize> {
+        self.encode(&mut IoEncoder(w))
+    }
+
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<usize, E::Error> {
+        e.emit(&self.version.to_le_bytes())?;
+        e.emit(&self.prev_blockhash)?;
+        e.emit(&self.merkle_root)?;
+        e.emit(&self.time.to_le_bytes())?;
+        e.emit(&self.bits.to_le_bytes())?;
+        e.emit(&self.nonce.to_le_bytes())?;
+        Ok(Self::SIZE)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<(Self, &[u8]), BitcoinError> {
+        let (value, consumed) = Self::from_bytes(bytes)?;
+        Ok((value, &bytes[consumed..]))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        if bytes.len() < Self::SIZE {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        let u32_at = |i: usize| [bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]];
+        let mut prev_blockhash = [0u8; 32];
+        prev_blockhash.copy_from_slice(&bytes[4..36]);
+        let mut merkle_root = [0u8; 32];
+        merkle_root.copy_from_slice(&bytes[36..68]);
+        let header = BlockHeader {
+            version: i32::from_le_bytes(u32_at(0)),
+            prev_blockhash,
+            merkle_root,
+            time: u32::from_le_bytes(u32_at(68)),
+            bits: u32::from_le_bytes(u32_at(72)),
+            nonce: u32::from_le_bytes(u32_at(76)),
+        };
+        Ok((header, Self::SIZE))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn read_from<R: Read>(r: &mut R) -> Result<Self, BitcoinError> {
+        let buf: [u8; 80] = read_array(r)?;
+        Ok(Self::from_bytes(&buf)?.0)
+    }
+
+    /// Double-SHA256 of the header in internal byte order; reverse for display.
+    pub fn block_hash(&self) -> [u8; 32] {
+        sha256d(&self.to_bytes())
+    }
+}
+
+/// Merkle root of txids given in internal byte order. Odd-length levels
+/// pair their last hash with itself. Returns `None` for an empty list.
+pub fn merkle_root(txids: &[[u8; 32]]) -> Option<[u8; 32]> {
+    let mut level = txids.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| {
+                let right = pair.get(1).unwrap_or(&pair[0]);
+                let mut buf = [0u8; 64];
+                buf[..32].copy_from_slice(&pair[0]);
+                buf[32..].copy_from_slice(right);
+                sha256d(&buf)
+            })
+            .collect();
+    }
+    level.first().copied()
+}
+
+/// Raw key-value pairs of one PSBT map, in the order they were read.
+pub type PsbtMap = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// A BIP174 partially signed transaction, kept at the byte level: the
+/// unsigned transaction is decoded, every other field is a raw key-value
+/// pair. The unsigned transaction is always written as the first global pair.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Psbt {
+    pub unsigned_tx: BitcoinTransaction,
+    /// Global pairs other than the unsigned transaction.
+    pub global: PsbtMap,
+    /// One map per input of `unsigned_tx`.
+    pub inputs: Vec<PsbtMap>,
+    /// One map per output of `unsigned_tx`.
+    pub outputs: Vec<PsbtMap>,
+}
+
+impl Psbt {
+    pub const MAGIC: [u8; 5] = *b"psbt\xff";
+    // Key of the global unsigned transaction: type 0x00, no key data.
+    const UNSIGNED_TX_KEY: [u8; 1] = [0x00];
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_to_vec(|e| self.encode(e))
+    }
+
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<usize, E::Error> {
+        e.emit(&Self::MAGIC)?;
+        let tx = self.unsigned_tx.to_bytes();
+        let mut written = Self::MAGIC.len() + encode_psbt_pair(&Self::UNSIGNED_TX_KEY, &tx, e)?;
+        written += encode_psbt_map(&self.global, e)?;
+        for map in self.inputs.iter().chain(&self.outputs) {
+            written += encode_psbt_map(map, e)?;
+        }
+        Ok(written)
+    }
+
+    /// Parses the magic, the global map and one map per input and output.
+    /// A missing, duplicated or signed unsigned transaction, or a duplicate
+    /// key within a map, is `InvalidFormat`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let magic = slice_at(bytes, 0, Self::MAGIC.len()).map_err(|e| e.at(0, "magic"))?;
+        if magic != Self::MAGIC {
+            return Err(BitcoinError::InvalidFormat.at(0, "magic"));
+        }
+        let mut offset = Self::MAGIC.len();
+        let (mut global, size) = slice_from(bytes, offset)
+            .and_then(psbt_map_from_bytes)
+            .map_err(|e| e.at(offset, "global map"))?;
+        let tx_pairs: Vec<usize> = global
+            .iter()
+            .enumerate()
+            .filter(|(_, (key, _))| key[..] == Self::UNSIGNED_TX_KEY)
+            .map(|(i, _)| i)
+            .collect();
+        let [tx_index] = tx_pairs[..] else {
+            return Err(BitcoinError::InvalidFormat.at(offset, "global map"));
+        };
+        let (_, tx_bytes) = global.remove(tx_index);
+        let unsigned_tx = BitcoinTransaction::from_bytes_strict(&tx_bytes)
+            .map_err(|e| e.at(offset, "unsigned tx"))?;
+        let signed = unsigned_tx
+            .inputs
+            .iter()
+            .any(|input| !input.script_sig.is_empty() || !input.witness.is_empty());
+        if signed {
+            return Err(BitcoinError::InvalidFormat.at(offset, "unsigned tx"));
+        }
+        offset = advance(offset, size).map_err(|e| e.at(offset, "global map"))?;
+
+        let mut maps = Vec::new();
+        for i in 0..unsigned_tx.inputs.len() + unsigned_tx.outputs.len() {
+            let field = if i < unsigned_tx.inputs.len() {
+                "input map"
+            } else {
+                "output map"
+            };
+            let (map, size) = slice_from(bytes, offset)
+                .and_then(psbt_map_from_bytes)
+                .map_err(|e| e.at(offset, field))?;
+            maps.push(map);
+            offset = advance(offset, size).map_err(|e| e.at(offset, field))?;
+        }
+        let outputs = maps.split_off(unsigned_tx.inputs.len());
+        let psbt = Psbt {
+            unsigned_tx,
+            global,
+            inputs: maps,
+            outputs,
+        };
+        Ok((psbt, offset))
+    }
+}
+
+// <key length> <key> <value length> <value>
+fn encode_psbt_pair<E: Encoder>(key: &[u8], value: &[u8], e: &mut E) -> Result<usize, E::Error> {
+    let mut written = CompactSize::new(key.len() as u64).encode(e)?;
+    e.emit(key)?;
+    written += CompactSize::new(value.len() as u64).encode(e)?;
+    e.emit(value)?;
+    Ok(written + key.len() + value.len())
+}
+
+// The pairs followed by the 0x00 separator.
+fn encode_psbt_map<E: Encoder>(map: &[(Vec<u8>, Vec<u8>)], e: &mut E) -> Result<usize, E::Error> {
+    let mut written = 0;
+    for (key, value) in map {
+        written += encode_psbt_pair(key, value, e)?;
+    }
+    e.emit(&[0x00])?;
+    Ok(written + 1)
+}
+
+fn psbt_map_from_bytes(bytes: &[u8]) -> Result<(PsbtMap, usize), BitcoinError> {
+    // CompactSize-prefixed field; returns it and the offset just past it
+    let field = |offset: usize| -> Result<(&[u8], usize), BitcoinError> {
+        let (len, size) = CompactSize::from_bytes(slice_from(bytes, offset)?)?;
+        let start = advance(offset, size)?;
+        let len = len.as_usize_checked()?;
+        Ok((slice_at(bytes, start, len)?, advance(start, len)?))
+    };
+    let mut map = PsbtMap::new();
+    let mut offset = 0;
+    loop {
+        let (key, next) = field(offset)?;
+        if key.is_empty() {
+            return Ok((map, next));
+        }
+        if map.iter().any(|(existing, _)| existing[..] == *key) {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let (value, next) = field(next)?;
+        map.push((key.to_vec(), value.to_vec()));
+        offset = next;
+    }
+}
+
+// Base58 of version || payload || first 4 bytes of sha256d(version || payload).
+fn base58check_encode(version: u8, payload: &[u8]) -> String {
+    const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+    let mut data = Vec::with_capacity(payload.len() + 5);
+    data.push(version);
+    data.extend_from_slice(payload);
+    let checksum = sha256d(&data);
+    data.extend_from_slice(&checksum[..4]);
+
+    // Base-58 digits, least significant first
+    let mut digits: Vec<u8> = Vec::new();
+    for &byte in &data {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    // Each leading zero byte is written as '1'
+    let zeros = data.iter().take_while(|&&b| b == 0).count();
+    core::iter::repeat_n('1', zeros)
+        .chain(digits.iter().rev().map(|&d| ALPHABET[d as usize] as char))
+        .collect()
+}
+
+#[cfg(feature = "serde")]
+impl BitcoinTransaction {
+    /// JSON in the shape of bitcoind's `decoderawtransaction`. Values are BTC
+    /// numbers and scriptSig ASM decodes sighash types. The `desc`, `address`
+    /// and `type` fields of `scriptPubKey` are not produced.
+    pub fn to_bitcoind_json(&self) -> serde_json::Value {
+        use serde_json::json;
+
+        let hex_id = |mut hash: [u8; 32]| {
+            hash.reverse();
+            hex::encode(hash)
+        };
+        let vin: Vec<serde_json::Value> = self
+            .inputs
+            .iter()
+            .map(|input| {
+                let mut entry = serde_json::Map::new();
+                if self.is_coinbase() {
+                    entry.insert(
+                        "coinbase".into(),
+                        json!(hex::encode(&input.script_sig.bytes)),
+                    );
+                } else {
+                    entry.insert("txid".into(), json!(input.previous_output.txid.to_hex()));
+                    entry.insert("vout".into(), json!(input.previous_output.vout));
+                    entry.insert(
+                        "scriptSig".into(),
+                        json!({
+                            "asm": input.script_sig.asm(true),
+                            "hex": hex::encode(&input.script_sig.bytes),
+                        }),
+                    );
+                }
+                if !input.witness.is_empty() {
+                    let items: Vec<String> = input.witness.iter().map(hex::encode).collect();
+                    entry.insert("txinwitness".into(), json!(items));
+                }
+                entry.insert("sequence".into(), json!(input.sequence));
+                serde_json::Value::Object(entry)
+            })
+            .collect();
+        let vout: Vec<serde_json::Value> = self
+            .outputs
+            .iter()
+            .enumerate()
+            .map(|(n, output)| {
+                json!({
+                    "value": output.value as f64 / Amount::SAT_PER_BTC as f64,
+                    "n": n,
+                    "scriptPubKey": {
+                        "asm": output.script_pubkey.to_asm(),
+                        "hex": hex::encode(&output.script_pubkey.bytes),
+                    },
+                })
+            })
+            .collect();
+        json!({
+            "txid": hex_id(self.txid()),
+            "hash": hex_id(self.wtxid()),
+            "version": self.version,
+            "size": self.total_size(),
+            "vsize": self.vsize(),
+            "weight": self.weight(),
+            "locktime": self.lock_time,
+            "vin": vin,
+            "vout": vout,
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for BitcoinTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // TODO: Format a user-friendly string showing version, inputs, lock_time
         // Display scriptSig length and bytes, and previous output info
         writeln!(f, "Version: {}", self.version)?;
         for (i, input) in self.inputs.iter().enumerate() {
@@ -317,7 +2978,7 @@ impl fmt::Display for BitcoinTransaction {
             writeln!(
                 f,
                 "  Previous Output Txid: {}",
-                hex::encode(input.previous_output.txid.0)
+                input.previous_output.txid.to_hex()
             )?;
             writeln!(f, "  Previous Output Vout: {}", input.previous_output.vout)?;
             writeln!(
@@ -326,8 +2987,26 @@ impl fmt::Display for BitcoinTransaction {
                 input.script_sig.bytes.len(),
                 hex::encode(&input.script_sig.bytes)
             )?;
+            writeln!(f, "  ScriptSig ASM: {}", input.script_sig)?;
 
             writeln!(f, "  Sequence: 0x{:08X}", input.sequence)?;
+            if !input.witness.is_empty() {
+                writeln!(f, "  Witness ({} items):", input.witness.len())?;
+                for item in &input.witness {
+                    writeln!(f, "    {}", hex::encode(item))?;
+                }
+            }
+        }
+        for (i, output) in self.outputs.iter().enumerate() {
+            writeln!(f, "Output #{}:", i)?;
+            writeln!(f, "  Value: {}", output.value)?;
+            writeln!(
+                f,
+                "  ScriptPubKey (len={}): {}",
+                output.script_pubkey.bytes.len(),
+                hex::encode(&output.script_pubkey.bytes)
+            )?;
+            writeln!(f, "  ScriptPubKey ASM: {}", output.script_pubkey)?;
         }
         writeln!(f, "Lock Time: {}", self.lock_time)?;
         Ok(())
@@ -1,15 +1,39 @@
 use rust_week_3_exercises::*;
+use std::collections::HashMap;
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    // Coinbase transaction of the genesis block.
+    const GENESIS_COINBASE_HEX: &str = "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+    // First bitcoin transfer (block 170).
+    const BLOCK_170_TX_HEX: &str = "0100000001c997a5e56e104102fa209c6a852dd90660a20b2d9c352423edce25857fcd3704000000004847304402204e45e16932b8af514961a1d3a1a25fdf3f4f7732e9d624c6c61548ab5fb8cd410220181522ec8eca07de4860a4acdd12909d831cc56cbbac4622082221a8768d1d0901ffffffff0200ca9a3b00000000434104ae1a62fe09c5f51b13905f07f06b99a2f7159b2225f374cd378d71302fa28414e7aab37397f554a7df5f142c21c1b7303b8a0626f1baded5c72a704f7e6cd84cac00286bee0000000043410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac00000000";
+
+    // Native P2WPKH example from BIP143, before signing.
+    const BIP143_P2WPKH_UNSIGNED_HEX: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+
+    // Signed native P2WPKH transaction from the BIP143 examples.
+    const BIP143_P2WPKH_SIGNED_HEX: &str = "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee635711000000";
+
+    fn parse_hex_tx(raw: &str) -> BitcoinTransaction {
+        let bytes = hex::decode(raw).unwrap();
+        let (tx, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(consumed, bytes.len());
+        tx
+    }
+
     fn dummy_txid(val: u8) -> [u8; 32] {
         let mut txid = [0u8; 32];
         txid[31] = val;
         txid
     }
 
+    fn script_from_hex(s: &str) -> Script {
+        Script::new(hex::decode(s).unwrap())
+    }
+
     #[test]
     fn test_compact_size_serialization() {
         let tests = vec![
@@ -34,6 +58,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_compact_size_rejects_non_minimal() {
+        let non_minimal: Vec<Vec<u8>> = vec![
+            vec![0xFD, 0x10, 0x00],
+            vec![0xFD, 0xFC, 0x00],
+            vec![0xFE, 0xFF, 0xFF, 0x00, 0x00],
+            vec![0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00],
+        ];
+        for bytes in non_minimal {
+            assert_eq!(
+                CompactSize::from_bytes(&bytes),
+                Err(BitcoinError::InvalidFormat)
+            );
+        }
+
+        let minimal: Vec<(Vec<u8>, u64)> = vec![
+            (vec![0xFD, 0xFD, 0x00], 0xFD),
+            (vec![0xFE, 0x00, 0x00, 0x01, 0x00], 0x10000),
+            (
+                vec![0xFF, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00],
+                0x100000000,
+            ),
+        ];
+        for (bytes, value) in minimal {
+            let (decoded, _) = CompactSize::from_bytes(&bytes).unwrap();
+            assert_eq!(decoded.value, value);
+        }
+    }
+
     #[test]
     fn test_outpoint_roundtrip() {
         let txid = dummy_txid(0xCC);
@@ -66,6 +119,23 @@ mod tests {
         assert_eq!(consumed, bytes.len());
     }
 
+    #[test]
+    fn test_tx_output_roundtrip() {
+        let script = Script::new(vec![0x76, 0xA9, 0x14, 0x88, 0xAC]);
+        let output = TransactionOutput::new(50_000, script);
+        let bytes = output.to_bytes();
+        assert_eq!(&bytes[..8], &50_000u64.to_le_bytes());
+        let (parsed, consumed) = TransactionOutput::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, output);
+        assert_eq!(consumed, bytes.len());
+    }
+
+    #[test]
+    fn test_tx_output_insufficient_bytes() {
+        let err = TransactionOutput::from_bytes(&[0x01, 0x02, 0x03]).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InsufficientBytes);
+    }
+
     #[test]
     fn test_bitcoin_tx_roundtrip() {
         let inputs = vec![TransactionInput::new(
@@ -73,21 +143,49 @@ mod tests {
             Script::new(vec![0x01, 0x02]),
             0xFFFFFFFF,
         )];
-        let tx = BitcoinTransaction::new(2, inputs.clone(), 1000);
+        let outputs = vec![
+            TransactionOutput::new(10_000, Script::new(vec![0x51])),
+            TransactionOutput::new(20_000, Script::new(vec![0x76, 0xA9, 0x88, 0xAC])),
+        ];
+        let tx = BitcoinTransaction::new(2, inputs.clone(), outputs, 1000);
+        let bytes = tx.to_bytes();
+        let (parsed, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, tx);
+        assert_eq!(parsed.outputs.len(), 2);
+        assert_eq!(consumed, bytes.len());
+    }
+
+    #[test]
+    fn test_bitcoin_tx_outputs_before_lock_time() {
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(2), 1),
+                Script::new(vec![]),
+                0,
+            )],
+            vec![TransactionOutput::new(1, Script::new(vec![]))],
+            0x0A0B0C0D,
+        );
         let bytes = tx.to_bytes();
+        // version + input count + input (36 + 1 + 4) + output count + output (8 + 1) + lock_time
+        assert_eq!(bytes.len(), 4 + 1 + 41 + 1 + 9 + 4);
+        assert_eq!(&bytes[bytes.len() - 4..], &[0x0D, 0x0C, 0x0B, 0x0A]);
         let (parsed, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
         assert_eq!(parsed, tx);
         assert_eq!(consumed, bytes.len());
     }
 
     #[test]
+    #[cfg(feature = "serde")]
     fn test_bitcoin_tx_json_serialization() {
         let input = TransactionInput::new(
             OutPoint::new(dummy_txid(0xAB), 3),
             Script::new(vec![0xDE, 0xAD, 0xBE, 0xEF]),
             0xABCDEF01,
         );
-        let tx = BitcoinTransaction::new(1, vec![input], 999);
+        let output = TransactionOutput::new(12_345, Script::new(vec![0x6A]));
+        let tx = BitcoinTransaction::new(1, vec![input], vec![output], 999);
 
         let json = serde_json::to_string_pretty(&tx).unwrap();
         let parsed: BitcoinTransaction = serde_json::from_str(&json).unwrap();
@@ -97,6 +195,7 @@ mod tests {
         assert!(json.contains("\"lock_time\": 999"));
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_bitcoin_transaction_display() {
         let input = TransactionInput::new(
@@ -104,10 +203,2431 @@ mod tests {
             Script::new(vec![0x01, 0x02, 0x03]),
             0xFFFFFFFF,
         );
-        let tx = BitcoinTransaction::new(1, vec![input], 0);
+        let output = TransactionOutput::new(5_000, Script::new(vec![0x51, 0x52]));
+        let tx = BitcoinTransaction::new(1, vec![input], vec![output], 0);
         let output = format!("{}", tx);
         assert!(output.contains("Version: 1"));
         assert!(output.contains("Lock Time: 0"));
         assert!(output.contains("Previous Output Vout: 7"));
+        assert!(output.contains("Output #0:"));
+        assert!(output.contains("Value: 5000"));
+        assert!(output.contains("ScriptPubKey (len=2)"));
+    }
+
+    #[test]
+    fn test_txid_matches_mainnet() {
+        let cases = [
+            (
+                GENESIS_COINBASE_HEX,
+                "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b",
+            ),
+            (
+                BLOCK_170_TX_HEX,
+                "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16",
+            ),
+        ];
+        for (raw, expected) in cases {
+            let tx = parse_hex_tx(raw);
+            assert_eq!(hex::encode(tx.to_bytes()), raw);
+            let mut txid = tx.txid();
+            txid.reverse();
+            assert_eq!(hex::encode(txid), expected);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_txid_json_uses_display_order() {
+        let tx = parse_hex_tx(GENESIS_COINBASE_HEX);
+        let txid = Txid(tx.txid());
+        let expected = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+        assert_eq!(txid.to_hex(), expected);
+        assert_eq!(
+            serde_json::to_string(&txid).unwrap(),
+            format!("\"{}\"", expected)
+        );
+        let parsed: Txid = serde_json::from_str(&format!("\"{}\"", expected)).unwrap();
+        assert_eq!(parsed, txid);
+        assert_eq!(Txid::from_hex(expected).unwrap(), txid);
+        assert_eq!(Txid::from_hex("abcd"), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[test]
+    fn test_bitcoin_error_display() {
+        assert_eq!(
+            BitcoinError::InsufficientBytes.to_string(),
+            "insufficient bytes"
+        );
+        assert_eq!(BitcoinError::InvalidFormat.to_string(), "invalid format");
+
+        fn parse(bytes: &[u8]) -> Result<CompactSize, Box<dyn std::error::Error>> {
+            Ok(CompactSize::from_bytes(bytes)?.0)
+        }
+        assert_eq!(parse(&[]).unwrap_err().to_string(), "insufficient bytes");
+    }
+
+    #[test]
+    fn test_segwit_tx_roundtrip() {
+        let tx = parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX);
+        assert!(tx.has_witness());
+        assert_eq!(tx.inputs.len(), 2);
+        assert_eq!(tx.outputs.len(), 2);
+        assert!(tx.inputs[0].witness.is_empty());
+        assert_eq!(tx.inputs[1].witness.len(), 2);
+        assert_eq!(tx.inputs[1].witness[1].len(), 33);
+        assert_eq!(tx.lock_time, 0x11);
+        assert_eq!(hex::encode(tx.to_bytes()), BIP143_P2WPKH_SIGNED_HEX);
+
+        let mut txid = tx.txid();
+        txid.reverse();
+        assert_eq!(
+            hex::encode(txid),
+            "e8151a2af31c368a35053ddd4bdb285a8595c769a3ad83e0fa02314a602d4609"
+        );
+    }
+
+    #[test]
+    fn test_segwit_marker_omitted_without_witness() {
+        let input = TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0);
+        let legacy = BitcoinTransaction::new(2, vec![input.clone()], vec![], 0);
+        assert_eq!(legacy.to_bytes()[4], 0x01);
+
+        let segwit =
+            BitcoinTransaction::new(2, vec![input.with_witness(vec![vec![0xAA; 3]])], vec![], 0);
+        let bytes = segwit.to_bytes();
+        assert_eq!(&bytes[4..6], &[0x00, 0x01]);
+        let (parsed, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, segwit);
+        assert_eq!(consumed, bytes.len());
+        assert_eq!(segwit.txid(), legacy.txid());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_read_from_streams_transactions() {
+        let legacy = parse_hex_tx(BLOCK_170_TX_HEX);
+        let segwit = parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX);
+        let mut stream = legacy.to_bytes();
+        stream.extend_from_slice(&segwit.to_bytes());
+
+        let mut reader = &stream[..];
+        assert_eq!(BitcoinTransaction::read_from(&mut reader).unwrap(), legacy);
+        assert_eq!(BitcoinTransaction::read_from(&mut reader).unwrap(), segwit);
+        assert!(reader.is_empty());
+
+        let mut cs_reader = &[0xFD, 0x00, 0x01][..];
+        assert_eq!(CompactSize::read_from(&mut cs_reader).unwrap().value, 0x100);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_read_from_truncated_is_insufficient_bytes() {
+        let bytes = parse_hex_tx(BLOCK_170_TX_HEX).to_bytes();
+        for len in [0, 3, 10, 50, bytes.len() - 1] {
+            let mut reader = &bytes[..len];
+            assert_eq!(
+                BitcoinTransaction::read_from(&mut reader),
+                Err(BitcoinError::InsufficientBytes)
+            );
+        }
+        let mut reader = &[0x05, 0x01, 0x02][..];
+        assert_eq!(
+            Script::read_from(&mut reader),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_write_to_matches_to_bytes() {
+        let tx = parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX);
+        let mut buf = Vec::new();
+        let written = tx.write_to(&mut buf).unwrap();
+        assert_eq!(written, buf.len());
+        assert_eq!(buf, tx.to_bytes());
+
+        let mut buf = Vec::new();
+        assert_eq!(CompactSize::new(0x10000).write_to(&mut buf).unwrap(), 5);
+        assert_eq!(
+            tx.inputs[0].write_to(&mut buf).unwrap(),
+            tx.inputs[0].to_bytes().len()
+        );
+        assert_eq!(tx.outputs[1].script_pubkey.write_to(&mut buf).unwrap(), 26);
+        assert_eq!(buf.len(), 5 + tx.inputs[0].to_bytes().len() + 26);
+    }
+
+    #[test]
+    fn test_from_bytes_strict() {
+        let mut bytes = hex::decode(BLOCK_170_TX_HEX).unwrap();
+        let tx = BitcoinTransaction::from_bytes_strict(&bytes).unwrap();
+        assert_eq!(tx.to_bytes(), bytes);
+
+        bytes.push(0x00);
+        assert_eq!(
+            BitcoinTransaction::from_bytes_strict(&bytes),
+            Err(BitcoinError::InvalidFormat)
+        );
+        let (_, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(consumed, bytes.len() - 1);
+    }
+
+    #[test]
+    fn test_huge_declared_counts_do_not_allocate() {
+        // version + 0xFF-prefixed input count of u64::MAX, with no inputs following
+        let mut bytes = vec![0x01, 0x00, 0x00, 0x00, 0xFF];
+        bytes.extend_from_slice(&[0xFF; 8]);
+        let err = BitcoinTransaction::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InsufficientBytes);
+
+        // one empty input, then a huge output count
+        let mut bytes = vec![0x01, 0x00, 0x00, 0x00, 0x01];
+        bytes.extend_from_slice(&[0x00; 36]);
+        bytes.extend_from_slice(&[0x00, 0xFF, 0xFF, 0xFF, 0xFF]);
+        bytes.extend_from_slice(&[0xFE, 0xFF, 0xFF, 0xFF, 0xFF]);
+        let err = BitcoinTransaction::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InsufficientBytes);
+    }
+
+    #[test]
+    fn test_weight_and_vsize() {
+        let segwit = parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX);
+        assert_eq!(segwit.base_size(), 233);
+        assert_eq!(segwit.total_size(), 343);
+        assert_eq!(segwit.weight(), 1042);
+        assert_eq!(segwit.vsize(), 261);
+
+        let legacy = parse_hex_tx(BLOCK_170_TX_HEX);
+        assert_eq!(legacy.base_size(), 275);
+        assert_eq!(legacy.total_size(), 275);
+        assert_eq!(legacy.weight(), 1100);
+        assert_eq!(legacy.vsize(), 275);
+    }
+
+    #[test]
+    fn test_is_coinbase() {
+        let coinbase = parse_hex_tx(GENESIS_COINBASE_HEX);
+        assert!(coinbase.inputs[0].previous_output.is_null());
+        assert!(coinbase.is_coinbase());
+
+        let spend = parse_hex_tx(BLOCK_170_TX_HEX);
+        assert!(!spend.inputs[0].previous_output.is_null());
+        assert!(!spend.is_coinbase());
+
+        assert!(!OutPoint::new([0u8; 32], 0).is_null());
+    }
+
+    #[test]
+    fn test_amount_money_cap_and_overflow() {
+        assert_eq!(MAX_MONEY, 2_100_000_000_000_000);
+        assert_eq!(Amount::from_sat(MAX_MONEY).unwrap().to_sat(), MAX_MONEY);
+        assert_eq!(Amount::from_sat(MAX_MONEY + 1), None);
+
+        let half = Amount::from_sat(MAX_MONEY / 2 + 1).unwrap();
+        assert_eq!(half.checked_add(half), None);
+        assert_eq!(Amount::ZERO.checked_sub(Amount::from_sat(1).unwrap()), None);
+        let sum = Amount::from_sat(10)
+            .unwrap()
+            .checked_add(Amount::from_sat(5).unwrap());
+        assert_eq!(sum.unwrap().to_sat(), 15);
+
+        assert_eq!(Amount::from_btc(0.5).unwrap().to_sat(), 50_000_000);
+        assert_eq!(Amount::from_btc(0.00000001).unwrap().to_sat(), 1);
+        assert_eq!(Amount::from_btc(-1.0), None);
+        assert_eq!(Amount::from_btc(21_000_001.0), None);
+        assert_eq!(Amount::from_btc(f64::NAN), None);
+        assert_eq!(Amount::from_sat(150_000_000).unwrap().to_btc(), 1.5);
+
+        let output = TransactionOutput::new(5_000, Script::new(vec![]));
+        assert_eq!(output.amount(), Amount::from_sat(5_000));
+        assert_eq!(
+            TransactionOutput::new(u64::MAX, Script::new(vec![])).amount(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_total_output_value_and_fee() {
+        let tx = parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX);
+        assert_eq!(tx.total_output_value(), 112_340_000 + 223_450_000);
+        assert_eq!(
+            tx.fee(&[625_000_000, 600_000_000]),
+            Ok(625_000_000 + 600_000_000 - 335_790_000)
+        );
+        // under-funded
+        assert_eq!(tx.fee(&[1_000, 2_000]), Err(BitcoinError::InvalidFormat));
+        // missing input value
+        assert_eq!(tx.fee(&[625_000_000]), Err(BitcoinError::InvalidFormat));
+        // overflowing input sum
+        assert_eq!(
+            tx.fee(&[u64::MAX, u64::MAX]),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        let overflowing = BitcoinTransaction::new(
+            1,
+            vec![],
+            vec![
+                TransactionOutput::new(u64::MAX, Script::new(vec![])),
+                TransactionOutput::new(1, Script::new(vec![])),
+            ],
+            0,
+        );
+        assert_eq!(overflowing.total_output_value(), u64::MAX);
+    }
+
+    #[test]
+    fn test_outpoint_from_str_and_display() {
+        let s = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16:1";
+        let outpoint: OutPoint = s.parse().unwrap();
+        assert_eq!(outpoint.vout, 1);
+        assert_eq!(outpoint.txid.0[0], 0x16);
+        assert_eq!(outpoint.txid.0[31], 0xf4);
+        assert_eq!(outpoint.to_string(), s);
+
+        let bad_hex_len = "f4184fc596403b9d638783cf57adfe4c:1";
+        assert_eq!(
+            bad_hex_len.parse::<OutPoint>(),
+            Err(BitcoinError::InvalidFormat)
+        );
+        let bad_vout = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16:x";
+        assert_eq!(
+            bad_vout.parse::<OutPoint>(),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            "no-colon".parse::<OutPoint>(),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_outpoints_as_map_keys() {
+        let mut utxos = HashMap::new();
+        for i in 0..4u8 {
+            utxos.insert(OutPoint::new(dummy_txid(i), i as u32), i as u64 * 1_000);
+        }
+        utxos.insert(OutPoint::new(dummy_txid(1), 7), 42);
+        assert_eq!(utxos.len(), 5);
+        assert_eq!(utxos.get(&OutPoint::new(dummy_txid(2), 2)), Some(&2_000));
+        assert_eq!(utxos.get(&OutPoint::new(dummy_txid(1), 7)), Some(&42));
+        assert_eq!(utxos.get(&OutPoint::new(dummy_txid(2), 3)), None);
+
+        let mut keys: Vec<OutPoint> = utxos.into_keys().collect();
+        keys.sort();
+        assert_eq!(keys[0], OutPoint::new(dummy_txid(0), 0));
+        assert!(Txid(dummy_txid(1)) < Txid(dummy_txid(2)));
+    }
+
+    #[test]
+    fn test_script_to_asm() {
+        let p2pkh =
+            Script::new(hex::decode("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac").unwrap());
+        assert_eq!(
+            p2pkh.to_asm(),
+            "OP_DUP OP_HASH160 62e907b15cbf27d5425399ebf6f0fb50ebb88f18 OP_EQUALVERIFY OP_CHECKSIG"
+        );
+
+        // OP_0, small ints, PUSHDATA1 and a short push rendered as a number
+        let mixed = Script::new(vec![
+            0x00, 0x52, 0x4c, 0x05, 1, 2, 3, 4, 5, 0x02, 0xE8, 0x03,
+        ]);
+        assert_eq!(mixed.to_asm(), "0 2 0102030405 1000");
+
+        // truncated push data
+        let truncated = Script::new(vec![0x76, 0x14, 0x01, 0x02]);
+        assert_eq!(truncated.to_asm(), "OP_DUP [error]");
+        assert_eq!(Script::new(vec![0x4d, 0x01]).to_asm(), "[error]");
+
+        #[cfg(feature = "std")]
+        {
+            let tx = parse_hex_tx(BLOCK_170_TX_HEX);
+            assert!(format!("{}", tx).contains("ScriptPubKey ASM: 04ae1a62fe09c5f51b13905f07f06b99a2f7159b2225f374cd378d71302fa28414e7aab37397f554a7df5f142c21c1b7303b8a0626f1baded5c72a704f7e6cd84c OP_CHECKSIG"));
+        }
+    }
+
+    #[test]
+    fn test_script_templates() {
+        let p2pkh = script_from_hex("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac");
+        let p2sh = script_from_hex("a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87");
+        let p2wpkh = script_from_hex("0014751e76e8199196d454941c45d1b3a323f1433bd6");
+        let p2wsh =
+            script_from_hex("00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262");
+        let op_return = script_from_hex("6a0b68656c6c6f20776f726c64");
+
+        assert!(p2pkh.is_p2pkh() && !p2pkh.is_p2sh());
+        assert!(p2sh.is_p2sh() && !p2sh.is_p2pkh());
+        assert!(p2wpkh.is_p2wpkh() && !p2wpkh.is_p2wsh());
+        assert!(p2wsh.is_p2wsh() && !p2wsh.is_p2wpkh());
+        assert!(op_return.is_op_return() && !p2pkh.is_op_return());
+
+        assert_eq!(p2pkh.address_type(), Some(AddressType::P2pkh));
+        assert_eq!(p2sh.address_type(), Some(AddressType::P2sh));
+        assert_eq!(p2wpkh.address_type(), Some(AddressType::P2wpkh));
+        assert_eq!(p2wsh.address_type(), Some(AddressType::P2wsh));
+        assert_eq!(op_return.address_type(), None);
+        // P2PK output from the genesis coinbase
+        let p2pk = &parse_hex_tx(GENESIS_COINBASE_HEX).outputs[0].script_pubkey;
+        assert_eq!(p2pk.address_type(), None);
+    }
+
+    #[test]
+    fn test_transaction_builder() {
+        let tx = TransactionBuilder::new()
+            .add_input(
+                OutPoint::new(dummy_txid(9), 1),
+                Script::new(vec![]),
+                0xFFFFFFFD,
+            )
+            .add_output(1_000, Script::new(vec![0x51]))
+            .add_output(2_000, Script::new(vec![0x52]))
+            .build();
+        assert_eq!(tx.version, 2);
+        assert_eq!(tx.lock_time, 0);
+
+        let mut expected = vec![0x02, 0x00, 0x00, 0x00, 0x01];
+        expected.extend_from_slice(&dummy_txid(9));
+        expected.extend_from_slice(&[0x01, 0x00, 0x00, 0x00, 0x00, 0xFD, 0xFF, 0xFF, 0xFF]);
+        expected.push(0x02);
+        expected.extend_from_slice(&[0xE8, 0x03, 0, 0, 0, 0, 0, 0, 0x01, 0x51]);
+        expected.extend_from_slice(&[0xD0, 0x07, 0, 0, 0, 0, 0, 0, 0x01, 0x52]);
+        expected.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+        assert_eq!(tx.to_bytes(), expected);
+
+        let tx = TransactionBuilder::default()
+            .version(1)
+            .lock_time(500)
+            .build();
+        assert_eq!((tx.version, tx.lock_time), (1, 500));
+    }
+
+    #[test]
+    fn test_sequence_helpers() {
+        let input = |sequence| {
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![]),
+                sequence,
+            )
+        };
+        assert!(input(0xFFFFFFFF).is_final());
+        assert!(!input(0xFFFFFFFE).is_final());
+
+        assert!(!input(0xFFFFFFFF).enables_rbf());
+        assert!(!input(0xFFFFFFFE).enables_rbf());
+        assert!(input(0xFFFFFFFD).enables_rbf());
+        assert!(input(0).enables_rbf());
+
+        assert_eq!(input(0xFFFFFFFF).relative_locktime(), None);
+        assert_eq!(
+            input(144).relative_locktime(),
+            Some(RelativeLockTime::Blocks(144))
+        );
+        assert_eq!(
+            input((1 << 22) | 10).relative_locktime(),
+            Some(RelativeLockTime::Time(10))
+        );
+        // bits outside the type flag and low 16 bits are ignored
+        assert_eq!(
+            input(0x0001_0005).relative_locktime(),
+            Some(RelativeLockTime::Blocks(5))
+        );
+    }
+
+    #[test]
+    fn test_lock_time_interpretation() {
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            Script::new(vec![]),
+            0xFFFFFFFE,
+        );
+        let mut tx = BitcoinTransaction::new(2, vec![input], vec![], 499_999_999);
+        assert_eq!(tx.parsed_lock_time(), LockTime::Height(499_999_999));
+        tx.lock_time = LOCK_TIME_THRESHOLD;
+        assert_eq!(tx.parsed_lock_time(), LockTime::Time(500_000_000));
+        assert!(tx.is_lock_time_enabled());
+
+        tx.inputs[0].sequence = 0xFFFFFFFF;
+        assert!(!tx.is_lock_time_enabled());
+        tx.inputs[0].sequence = 0;
+        tx.lock_time = 0;
+        assert!(!tx.is_lock_time_enabled());
+    }
+
+    #[test]
+    fn test_transaction_hex_roundtrip() {
+        let tx = BitcoinTransaction::from_hex(BIP143_P2WPKH_SIGNED_HEX).unwrap();
+        assert_eq!(tx.to_hex(), BIP143_P2WPKH_SIGNED_HEX);
+        assert_eq!(tx, parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX));
+
+        assert_eq!(
+            BitcoinTransaction::from_hex("0100zz"),
+            Err(BitcoinError::InvalidFormat)
+        );
+        let err = BitcoinTransaction::from_hex(&BLOCK_170_TX_HEX[..20]).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InsufficientBytes);
+    }
+
+    #[test]
+    fn test_txid_null() {
+        assert!(Txid::null().is_null());
+        assert_eq!(Txid::null(), Txid([0u8; 32]));
+        assert!(!Txid(dummy_txid(1)).is_null());
+        assert!(
+            OutPoint {
+                txid: Txid::null(),
+                vout: u32::MAX
+            }
+            .is_null()
+        );
+    }
+
+    #[test]
+    fn test_parse_error_reports_offset() {
+        let bytes = hex::decode(BLOCK_170_TX_HEX).unwrap();
+        // version (4) + input count (1) + outpoint (36) + script length (1) + 20 of 72 script bytes
+        let err = BitcoinTransaction::from_bytes(&bytes[..62]).unwrap_err();
+        assert_eq!(
+            err,
+            BitcoinError::ParseError {
+                offset: 41,
+                field: "script_sig",
+                kind: Box::new(BitcoinError::InsufficientBytes),
+            }
+        );
+        assert_eq!(err.kind(), &BitcoinError::InsufficientBytes);
+        assert_eq!(
+            err.to_string(),
+            "insufficient bytes at byte 41 (script_sig)"
+        );
+
+        // cut inside the lock time
+        let err = BitcoinTransaction::from_bytes(&bytes[..bytes.len() - 2]).unwrap_err();
+        assert_eq!(
+            err,
+            BitcoinError::ParseError {
+                offset: bytes.len() - 4,
+                field: "lock_time",
+                kind: Box::new(BitcoinError::InsufficientBytes),
+            }
+        );
+
+        // non-minimal output count
+        let mut tx = parse_hex_tx(GENESIS_COINBASE_HEX).to_bytes();
+        let output_count_at = 4 + 1 + 36 + 1 + 0x4d + 4;
+        assert_eq!(tx[output_count_at], 0x01);
+        tx.splice(output_count_at..=output_count_at, [0xFD, 0x01, 0x00]);
+        let err = BitcoinTransaction::from_bytes(&tx).unwrap_err();
+        assert_eq!(
+            err,
+            BitcoinError::ParseError {
+                offset: output_count_at,
+                field: "output count",
+                kind: Box::new(BitcoinError::InvalidFormat),
+            }
+        );
+    }
+
+    #[test]
+    fn test_compact_size_encoded_len() {
+        let cases = [
+            (0u64, 1),
+            (0xFC, 1),
+            (0xFD, 3),
+            (0xFFFF, 3),
+            (0x10000, 5),
+            (0xFFFF_FFFF, 5),
+            (0x1_0000_0000, 9),
+            (u64::MAX, 9),
+        ];
+        for (value, len) in cases {
+            let cs = CompactSize::new(value);
+            assert_eq!(cs.encoded_len(), len, "value {:#x}", value);
+            assert_eq!(cs.to_bytes().len(), len, "value {:#x}", value);
+        }
+    }
+
+    #[test]
+    fn test_serialized_size_matches_to_bytes() {
+        let big_script = Script::new(vec![0x51; 300]);
+        let built = TransactionBuilder::new()
+            .add_input(
+                OutPoint::new(dummy_txid(7), 1),
+                big_script.clone(),
+                0xFFFFFFFF,
+            )
+            .add_output(1_000, big_script)
+            .build();
+        let txs = [
+            parse_hex_tx(GENESIS_COINBASE_HEX),
+            parse_hex_tx(BLOCK_170_TX_HEX),
+            parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX),
+            built,
+            BitcoinTransaction::new(1, vec![], vec![], 0),
+        ];
+        for tx in &txs {
+            assert_eq!(tx.serialized_size(), tx.to_bytes().len());
+        }
+    }
+
+    #[test]
+    fn test_script_size_limit() {
+        // MAX_SCRIPT_SIZE only applies to execution, so longer scripts parse
+        let over_limit = Script::new(vec![0x61; MAX_SCRIPT_SIZE + 1]).to_bytes();
+        let (script, consumed) = Script::from_bytes(&over_limit).unwrap();
+        assert_eq!(script.len(), MAX_SCRIPT_SIZE + 1);
+        assert_eq!(consumed, over_limit.len());
+
+        // An output over the limit, e.g. a large OP_RETURN, is still a valid transaction
+        let mut big_op_return = vec![opcodes::OP_RETURN];
+        big_op_return.resize(MAX_SCRIPT_SIZE + 1, 0x00);
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![]),
+                0xFFFFFFFF,
+            )],
+            vec![TransactionOutput::new(0, Script::new(big_op_return))],
+            0,
+        );
+        let bytes = tx.to_bytes();
+        assert_eq!(
+            BitcoinTransaction::from_bytes(&bytes),
+            Ok((tx, bytes.len()))
+        );
+
+        // A length no transaction could hold is rejected from the prefix alone
+        assert_eq!(
+            Script::from_bytes(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_script_builder_pushes() {
+        let hash = [0x11u8; 20];
+        let p2pkh = Script::builder()
+            .push_opcode(opcodes::OP_DUP)
+            .push_opcode(opcodes::OP_HASH160)
+            .push_slice(&hash)
+            .push_opcode(opcodes::OP_EQUALVERIFY)
+            .push_opcode(opcodes::OP_CHECKSIG)
+            .into_script();
+        let mut expected = vec![0x76, 0xa9, 0x14];
+        expected.extend_from_slice(&hash);
+        expected.extend_from_slice(&[0x88, 0xac]);
+        assert_eq!(p2pkh.bytes, expected);
+        assert!(p2pkh.is_p2pkh());
+
+        let cases: [(usize, &[u8]); 5] = [
+            (75, &[75]),
+            (76, &[0x4c, 76]),
+            (255, &[0x4c, 0xff]),
+            (256, &[0x4d, 0x00, 0x01]),
+            (0x10000, &[0x4e, 0x00, 0x00, 0x01, 0x00]),
+        ];
+        for (len, prefix) in cases {
+            let data = vec![0xab; len];
+            let script = Script::builder().push_slice(&data).into_script();
+            assert_eq!(&script.bytes[..prefix.len()], prefix, "len {}", len);
+            assert_eq!(&script.bytes[prefix.len()..], &data[..]);
+        }
+    }
+
+    #[test]
+    fn test_script_as_ref_and_from() {
+        fn byte_len(data: impl AsRef<[u8]>) -> usize {
+            data.as_ref().len()
+        }
+        let script = Script::from(vec![0x6a, 0x01, 0x2a]);
+        assert_eq!(byte_len(&script), 3);
+        assert_eq!(hex::encode(&script), "6a012a");
+        assert_eq!(Script::from(&[0x6a, 0x01, 0x2a][..]), script);
+    }
+
+    #[test]
+    fn test_wtxid() {
+        let tx = parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX);
+        let mut wtxid = tx.wtxid();
+        wtxid.reverse();
+        assert_eq!(
+            hex::encode(wtxid),
+            "c36c38370907df2324d9ce9d149d191192f338b37665a82e78e76a12c909b762"
+        );
+        assert_ne!(tx.wtxid(), tx.txid());
+
+        let legacy = parse_hex_tx(BLOCK_170_TX_HEX);
+        assert_eq!(legacy.wtxid(), legacy.txid());
+    }
+
+    #[test]
+    fn test_genesis_block_header() {
+        let raw = hex::decode(
+            "0100000000000000000000000000000000000000000000000000000000000000\
+             000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa\
+             4b1e5e4a29ab5f49ffff001d1dac2b7c",
+        )
+        .unwrap();
+        let (header, consumed) = BlockHeader::from_bytes(&raw).unwrap();
+        assert_eq!(consumed, 80);
+        assert_eq!(header.version, 1);
+        assert_eq!(header.prev_blockhash, [0u8; 32]);
+        assert_eq!(header.time, 1231006505);
+        assert_eq!(header.bits, 0x1d00ffff);
+        assert_eq!(header.nonce, 2083236893);
+        assert_eq!(header.to_bytes(), raw);
+
+        // The genesis header commits to its single coinbase transaction
+        let coinbase = parse_hex_tx(GENESIS_COINBASE_HEX);
+        assert_eq!(header.merkle_root, coinbase.txid());
+
+        let mut hash = header.block_hash();
+        hash.reverse();
+        assert_eq!(
+            hex::encode(hash),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+
+        assert_eq!(
+            BlockHeader::from_bytes(&raw[..79]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_merkle_root_block_100000() {
+        let txids: Vec<[u8; 32]> = [
+            "8c14f0db3df150123e6f3dbbf30f8b955a8249b62ac1d1ff16284aefa3d06d87",
+            "fff2525b8931402dd09222c50775608f75787bd2b87e56995a7bdd30f79702c4",
+            "6359f0868171b1d194cbee1af2f16ea598ae8fad666d9b012c8ed2b79a236ec4",
+            "e9a66845e05d5abc0ad04ec80f774a7e585c6e8db975962d069a522137b80c1d",
+        ]
+        .iter()
+        .map(|s| Txid::from_hex(s).unwrap().0)
+        .collect();
+        let root = merkle_root(&txids).unwrap();
+        assert_eq!(
+            Txid(root).to_hex(),
+            "f3e94742aca4b5ef85488dc37c06c3282295ffec960994b2c0d5ac2a25a95766"
+        );
+        // Odd count duplicates the last txid
+        assert_eq!(
+            Txid(merkle_root(&txids[..3]).unwrap()).to_hex(),
+            "fa435470825de273081dcc706b25514c936fa6dc80ab965ce6970d68ddd0b553"
+        );
+        assert_eq!(merkle_root(&txids[..1]), Some(txids[0]));
+        assert_eq!(merkle_root(&[]), None);
+    }
+
+    #[test]
+    fn test_component_byte_ranges() {
+        for raw in [BLOCK_170_TX_HEX, BIP143_P2WPKH_SIGNED_HEX] {
+            let tx = parse_hex_tx(raw);
+            let bytes = tx.to_bytes();
+            let inputs = tx.input_offsets();
+            let outputs = tx.output_offsets();
+            assert_eq!(inputs.len(), tx.inputs.len());
+            assert_eq!(outputs.len(), tx.outputs.len());
+            for (input, range) in tx.inputs.iter().zip(&inputs) {
+                let (parsed, _) = TransactionInput::from_bytes(&bytes[range.clone()]).unwrap();
+                assert_eq!(parsed.previous_output, input.previous_output);
+                assert_eq!(parsed.script_sig, input.script_sig);
+            }
+            for (output, range) in tx.outputs.iter().zip(&outputs) {
+                assert_eq!(&bytes[range.clone()], &output.to_bytes()[..]);
+            }
+            // Ranges are contiguous, separated only by the output count
+            for pair in inputs.windows(2).chain(outputs.windows(2)) {
+                assert_eq!(pair[0].end, pair[1].start);
+            }
+            assert_eq!(inputs.last().unwrap().end + 1, outputs[0].start);
+        }
+    }
+
+    #[test]
+    fn test_compact_size_ordering() {
+        let mut sizes: Vec<CompactSize> = [0x10000u64, 3, 0xFD, 0]
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        sizes.sort();
+        let values: Vec<u64> = sizes.into_iter().map(u64::from).collect();
+        assert_eq!(values, vec![0, 3, 0xFD, 0x10000]);
+        assert!(CompactSize::new(0xFC) < CompactSize::from(0xFD));
+    }
+
+    #[test]
+    fn test_txid_from_hex_validation() {
+        let hex = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+        let txid = Txid::from_hex(hex).unwrap();
+        assert_eq!(txid.0[0], 0x16);
+        assert_eq!(txid.to_hex(), hex);
+
+        // wrong length, odd length, and non-hex characters
+        assert_eq!(Txid::from_hex(&hex[..62]), Err(BitcoinError::InvalidFormat));
+        assert_eq!(Txid::from_hex(&hex[..63]), Err(BitcoinError::InvalidFormat));
+        assert_eq!(
+            Txid::from_hex(&format!("{}00", hex)),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(Txid::from_hex(""), Err(BitcoinError::InvalidFormat));
+        let bad_char = format!("{}zz", &hex[..62]);
+        assert_eq!(Txid::from_hex(&bad_char), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[test]
+    fn test_zero_input_marker_disambiguation() {
+        // version, zero inputs, zero outputs, lock time
+        let empty = hex::decode("01000000000000000000").unwrap();
+        let tx = BitcoinTransaction::from_bytes_strict(&empty).unwrap();
+        assert!(tx.inputs.is_empty() && tx.outputs.is_empty());
+        assert_eq!(tx.to_bytes(), empty);
+
+        // 0x00 followed by 0x01 is the segwit marker
+        let segwit = parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX).to_bytes();
+        assert_eq!(&segwit[4..6], &[0x00, 0x01]);
+        assert!(
+            BitcoinTransaction::from_bytes_strict(&segwit)
+                .unwrap()
+                .has_witness()
+        );
+
+        // any other flag after the marker is rejected
+        let mut bad_flag = segwit.clone();
+        bad_flag[5] = 0x02;
+        let err = BitcoinTransaction::from_bytes(&bad_flag).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InvalidFormat);
+        #[cfg(feature = "std")]
+        {
+            assert_eq!(
+                BitcoinTransaction::read_from(&mut &bad_flag[..]),
+                Err(BitcoinError::InvalidFormat)
+            );
+            assert_eq!(BitcoinTransaction::read_from(&mut &empty[..]).unwrap(), tx);
+        }
+    }
+
+    #[test]
+    fn test_script_hash_extraction() {
+        let p2pkh = script_from_hex("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac");
+        assert_eq!(
+            hex::encode(p2pkh.p2pkh_hash().unwrap()),
+            "62e907b15cbf27d5425399ebf6f0fb50ebb88f18"
+        );
+        assert_eq!(p2pkh.p2sh_hash(), None);
+        assert_eq!(p2pkh.witness_program(), None);
+
+        let p2sh = script_from_hex("a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87");
+        assert_eq!(
+            hex::encode(p2sh.p2sh_hash().unwrap()),
+            "b472a266d0bd89c13706a4132ccfb16f7c3b9fcb"
+        );
+        assert_eq!(p2sh.p2pkh_hash(), None);
+
+        let p2wpkh = script_from_hex("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1");
+        let (version, program) = p2wpkh.witness_program().unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(
+            hex::encode(program),
+            "1d0f172a0ecb48aee1be1f2687d2963ae33f71a1"
+        );
+
+        let p2tr =
+            script_from_hex("5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c");
+        let (version, program) = p2tr.witness_program().unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(program.len(), 32);
+
+        // push length must cover the rest of the script exactly
+        assert_eq!(script_from_hex("0014aabb").witness_program(), None);
+        assert_eq!(script_from_hex("6a0401020304").witness_program(), None);
+    }
+
+    #[test]
+    fn test_impossible_input_count_fails_fast() {
+        // 3 declared inputs need at least 123 bytes; only 100 follow
+        let mut bytes = vec![0x02, 0x00, 0x00, 0x00, 0x03];
+        bytes.extend_from_slice(&[0x00; 100]);
+        assert_eq!(
+            BitcoinTransaction::from_bytes(&bytes),
+            Err(BitcoinError::ParseError {
+                offset: 4,
+                field: "input count",
+                kind: Box::new(BitcoinError::InsufficientBytes),
+            })
+        );
+
+        let mut huge = vec![0x02, 0x00, 0x00, 0x00];
+        huge.extend_from_slice(&[0xFF; 9]);
+        let err = BitcoinTransaction::from_bytes(&huge).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InsufficientBytes);
+    }
+
+    #[test]
+    fn test_bip143_signature_hash() {
+        let tx = parse_hex_tx(BIP143_P2WPKH_UNSIGNED_HEX);
+        let script_code = script_from_hex("76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac");
+        let value = 600_000_000;
+        let sighash = tx
+            .signature_hash_segwit(1, &script_code, value, SighashType::All)
+            .unwrap();
+        assert_eq!(
+            hex::encode(sighash),
+            "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"
+        );
+
+        // other types, cross-checked against an independent implementation
+        let cases = [
+            (
+                SighashType::None,
+                "6ff11a9b87fb510a3a31af006bd3811b632f8a39d88a2bfda49cee203dcc356e",
+            ),
+            (
+                SighashType::Single,
+                "f4fe57286dd2ca8ac0e3dfccd54c352fcdcacbed80f194e264b75d7a7c74e4ce",
+            ),
+            (
+                SighashType::AllPlusAnyoneCanPay,
+                "fc5b6bbc855883bcfdaefb77071740ccde4929f15e6a13286584e779b2529d91",
+            ),
+        ];
+        for (sighash_type, expected) in cases {
+            let sighash = tx
+                .signature_hash_segwit(1, &script_code, value, sighash_type)
+                .unwrap();
+            assert_eq!(hex::encode(sighash), expected);
+        }
+
+        assert_eq!(
+            tx.signature_hash_segwit(2, &script_code, value, SighashType::All),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_legacy_signature_hash() {
+        // Block 170 spends a P2PK output; its signature verifies against this hash
+        let tx = parse_hex_tx(BLOCK_170_TX_HEX);
+        let prev_script_pubkey = script_from_hex(
+            "410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac",
+        );
+        assert_eq!(
+            hex::encode(
+                tx.signature_hash_legacy(0, &prev_script_pubkey, SighashType::All)
+                    .unwrap()
+            ),
+            "7a05c6145f10101e9d6325494245adf1297d80f8f38d4d576d57cdba220bcb19"
+        );
+
+        // Other sighash types, cross-checked against an independent
+        // implementation; OP_CODESEPARATORs are dropped from the script code
+        let tx = parse_hex_tx(BIP143_P2WPKH_UNSIGNED_HEX);
+        let script_code = script_from_hex("ab76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a1ab88ac");
+        let cases = [
+            (
+                0,
+                SighashType::All,
+                "47194bc3c303a30aa5f78e45c7c2980b3be1284a9d69b1ea9ec0d29aac5f6848",
+            ),
+            (
+                1,
+                SighashType::None,
+                "ffbbcf554debe55f76a79db7d205edc891f194184a93a660366bb8f7facb89e2",
+            ),
+            (
+                1,
+                SighashType::Single,
+                "33cd468bd6b82f04bcef180b748c521d6fdee3b11711a2f27b2e465915afaec2",
+            ),
+            (
+                0,
+                SighashType::AllPlusAnyoneCanPay,
+                "4e7de48ff097d47bb87912759ec9380049a160289f2b89d48a28887ee30a41d4",
+            ),
+            (
+                1,
+                SighashType::SinglePlusAnyoneCanPay,
+                "865c7791b88917498a4c402176c302f146c53a6c2f50ecda08548f515237dca6",
+            ),
+        ];
+        for (index, sighash_type, expected) in cases {
+            let sighash = tx
+                .signature_hash_legacy(index, &script_code, sighash_type)
+                .unwrap();
+            assert_eq!(hex::encode(sighash), expected);
+        }
+
+        // SIGHASH_SINGLE with no output at the input's index
+        let mut three_inputs = tx.clone();
+        three_inputs.inputs.push(tx.inputs[0].clone());
+        let mut one = [0u8; 32];
+        one[0] = 1;
+        assert_eq!(
+            three_inputs.signature_hash_legacy(2, &script_code, SighashType::Single),
+            Ok(one)
+        );
+        assert_eq!(
+            tx.signature_hash_legacy(2, &script_code, SighashType::All),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_borrowed_script_and_input() {
+        let bytes = hex::decode(BLOCK_170_TX_HEX).unwrap();
+        let input_bytes = &bytes[5..];
+        let (input, consumed) = TransactionInput::from_bytes_ref(input_bytes).unwrap();
+        let script_start = &input_bytes[37];
+        // The scriptSig points into the original buffer rather than a copy
+        assert!(core::ptr::eq(input.script_sig.0.as_ptr(), script_start));
+        assert_eq!(input.script_sig.0.len(), 0x48);
+
+        let owned = TransactionInput::from_bytes(input_bytes).unwrap();
+        assert_eq!(owned, (input.to_input(), consumed));
+
+        let (script, consumed) = Script::from_bytes_ref(&[0x02, 0x51, 0x87, 0xff]).unwrap();
+        assert_eq!(script, ScriptRef(&[0x51, 0x87]));
+        assert_eq!(consumed, 3);
+        assert_eq!(script.to_script(), Script::new(vec![0x51, 0x87]));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_bitcoind_json_matches_decoderawtransaction() {
+        // decoderawtransaction output for the block 170 transaction, minus the
+        // scriptPubKey desc/type fields
+        let expected: serde_json::Value = serde_json::from_str(
+            r#"{
+              "txid": "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16",
+              "hash": "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16",
+              "version": 1,
+              "size": 275,
+              "vsize": 275,
+              "weight": 1100,
+              "locktime": 0,
+              "vin": [
+                {
+                  "txid": "0437cd7f8525ceed2324359c2d0ba26006d92d856a9c20fa0241106ee5a597c9",
+                  "vout": 0,
+                  "scriptSig": {
+                    "asm": "304402204e45e16932b8af514961a1d3a1a25fdf3f4f7732e9d624c6c61548ab5fb8cd410220181522ec8eca07de4860a4acdd12909d831cc56cbbac4622082221a8768d1d09[ALL]",
+                    "hex": "47304402204e45e16932b8af514961a1d3a1a25fdf3f4f7732e9d624c6c61548ab5fb8cd410220181522ec8eca07de4860a4acdd12909d831cc56cbbac4622082221a8768d1d0901"
+                  },
+                  "sequence": 4294967295
+                }
+              ],
+              "vout": [
+                {
+                  "value": 10.00000000,
+                  "n": 0,
+                  "scriptPubKey": {
+                    "asm": "04ae1a62fe09c5f51b13905f07f06b99a2f7159b2225f374cd378d71302fa28414e7aab37397f554a7df5f142c21c1b7303b8a0626f1baded5c72a704f7e6cd84c OP_CHECKSIG",
+                    "hex": "4104ae1a62fe09c5f51b13905f07f06b99a2f7159b2225f374cd378d71302fa28414e7aab37397f554a7df5f142c21c1b7303b8a0626f1baded5c72a704f7e6cd84cac"
+                  }
+                },
+                {
+                  "value": 40.00000000,
+                  "n": 1,
+                  "scriptPubKey": {
+                    "asm": "0411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3 OP_CHECKSIG",
+                    "hex": "410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac"
+                  }
+                }
+              ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(parse_hex_tx(BLOCK_170_TX_HEX).to_bitcoind_json(), expected);
+
+        let coinbase = parse_hex_tx(GENESIS_COINBASE_HEX).to_bitcoind_json();
+        assert!(
+            coinbase["vin"][0]["coinbase"]
+                .as_str()
+                .unwrap()
+                .starts_with("04ffff001d")
+        );
+        assert!(coinbase["vin"][0].get("txid").is_none());
+
+        let segwit = parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX).to_bitcoind_json();
+        assert_eq!(segwit["vin"][1]["txinwitness"].as_array().unwrap().len(), 2);
+        assert!(segwit["vin"][0].get("txinwitness").is_none());
+        assert_eq!(segwit["weight"], 1042);
+    }
+
+    #[test]
+    fn test_compact_size_prefix_byte() {
+        let cases = [
+            (0u64, None),
+            (CompactSize::MAX_SINGLE_BYTE, None),
+            (0xFD, Some(CompactSize::PREFIX_U16)),
+            (CompactSize::MAX_U16, Some(0xFD)),
+            (CompactSize::MAX_U16 + 1, Some(CompactSize::PREFIX_U32)),
+            (CompactSize::MAX_U32, Some(0xFE)),
+            (CompactSize::MAX_U32 + 1, Some(CompactSize::PREFIX_U64)),
+            (CompactSize::MAX, Some(0xFF)),
+        ];
+        for (value, prefix) in cases {
+            let cs = CompactSize::new(value);
+            assert_eq!(cs.prefix_byte(), prefix, "value {:#x}", value);
+            if let Some(prefix) = prefix {
+                assert_eq!(cs.to_bytes()[0], prefix);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parsers_never_panic_on_arbitrary_input() {
+        fn parse_all(bytes: &[u8]) {
+            let _ = CompactSize::from_bytes(bytes);
+            let _ = OutPoint::from_bytes(bytes);
+            let _ = Script::from_bytes(bytes);
+            let _ = TransactionInput::from_bytes(bytes);
+            let _ = TransactionOutput::from_bytes(bytes);
+            let _ = BitcoinTransaction::from_bytes(bytes);
+            #[cfg(feature = "std")]
+            let _ = BitcoinTransaction::read_from(&mut &bytes[..]);
+        }
+
+        // every truncation of real transactions
+        for raw in [
+            GENESIS_COINBASE_HEX,
+            BLOCK_170_TX_HEX,
+            BIP143_P2WPKH_SIGNED_HEX,
+        ] {
+            let bytes = hex::decode(raw).unwrap();
+            for end in 0..=bytes.len() {
+                parse_all(&bytes[..end]);
+            }
+        }
+
+        // pseudo-random buffers and single-byte corruptions of a real transaction
+        let mut state = 0x2545F4914F6CDD1Du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..2_000 {
+            let len = (next() % 300) as usize;
+            let buf: Vec<u8> = (0..len).map(|_| next() as u8).collect();
+            parse_all(&buf);
+        }
+        let segwit = hex::decode(BIP143_P2WPKH_SIGNED_HEX).unwrap();
+        for _ in 0..2_000 {
+            let mut buf = segwit.clone();
+            let i = (next() as usize) % buf.len();
+            buf[i] = next() as u8;
+            parse_all(&buf);
+        }
+    }
+
+    #[test]
+    fn test_tx_input_short_after_outpoint() {
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(3), 7),
+            Script::new(vec![0x51, 0x52, 0x53]),
+            0xFFFFFFFF,
+        );
+        let bytes = input.to_bytes();
+        // outpoint only, outpoint plus part of the script, and a missing sequence byte
+        for (end, field) in [(36, "script_sig"), (38, "script_sig"), (43, "sequence")] {
+            let err = TransactionInput::from_bytes(&bytes[..end]).unwrap_err();
+            assert_eq!(err.kind(), &BitcoinError::InsufficientBytes, "end {}", end);
+            assert!(matches!(err, BitcoinError::ParseError { field: f, .. } if f == field));
+        }
+        assert_eq!(TransactionInput::from_bytes(&bytes).unwrap().1, 44);
+    }
+
+    #[test]
+    fn test_script_display_is_asm() {
+        let p2pkh = script_from_hex("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac");
+        assert_eq!(
+            p2pkh.to_string(),
+            "OP_DUP OP_HASH160 62e907b15cbf27d5425399ebf6f0fb50ebb88f18 OP_EQUALVERIFY OP_CHECKSIG"
+        );
+        // push claims 5 bytes but only 2 follow
+        assert_eq!(script_from_hex("7605aabb").to_string(), "OP_DUP [error]");
+    }
+
+    #[test]
+    fn test_coinbase_height() {
+        // Start of the coinbase scriptSig in block 227836, the first BIP34 block
+        let coinbase_input = |script_hex: &str| {
+            TransactionInput::new(
+                OutPoint::new([0u8; 32], u32::MAX),
+                script_from_hex(script_hex),
+                u32::MAX,
+            )
+        };
+        let input = coinbase_input("03fc7903062f503253482f");
+        assert_eq!(input.coinbase_height(), Some(227_836));
+        assert_eq!(coinbase_input("5b").coinbase_height(), Some(11));
+
+        // truncated push and negative number
+        assert_eq!(coinbase_input("03fc79").coinbase_height(), None);
+        assert_eq!(coinbase_input("0181").coinbase_height(), None);
+        // Pre-BIP34 coinbases just yield whatever their first push holds
+        let genesis = parse_hex_tx(GENESIS_COINBASE_HEX);
+        assert_eq!(genesis.inputs[0].coinbase_height(), Some(0x1d00ffff));
+
+        let spend = parse_hex_tx(BLOCK_170_TX_HEX);
+        assert_eq!(spend.inputs[0].coinbase_height(), None);
+    }
+
+    #[test]
+    fn test_is_standard_version() {
+        for (version, standard) in [
+            (0, false),
+            (1, true),
+            (2, true),
+            (3, false),
+            (u32::MAX, false),
+        ] {
+            let tx = TransactionBuilder::new().version(version).build();
+            assert_eq!(tx.is_standard_version(), standard, "version {}", version);
+        }
+    }
+
+    #[test]
+    fn test_prevouts_in_input_order() {
+        let tx = parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX);
+        let prevouts = tx.prevouts();
+        assert_eq!(prevouts.len(), 2);
+        for (prevout, input) in prevouts.iter().zip(&tx.inputs) {
+            assert!(core::ptr::eq(*prevout, &input.previous_output));
+        }
+        assert_eq!(prevouts[0].vout, 0);
+        assert_eq!(prevouts[1].vout, 1);
+    }
+
+    #[test]
+    fn test_txid_from_str_and_display() {
+        let s = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+        let txid: Txid = s.parse().unwrap();
+        assert_eq!(txid.0, parse_hex_tx(BLOCK_170_TX_HEX).txid());
+        assert_eq!(txid.to_string(), s);
+        assert_eq!(
+            "not a txid".parse::<Txid>(),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_with_script_sig() {
+        let tx = parse_hex_tx(BLOCK_170_TX_HEX);
+        let replacement = Script::new(vec![0x51]);
+        let updated = tx.with_script_sig(0, replacement.clone()).unwrap();
+        assert_eq!(updated.inputs[0].script_sig, replacement);
+        assert_eq!(updated.outputs, tx.outputs);
+        assert_eq!(tx.inputs[0].script_sig.len(), 0x48);
+
+        assert_eq!(
+            tx.with_script_sig(1, replacement),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        let cleared = tx.with_all_script_sigs_cleared();
+        assert!(
+            cleared
+                .inputs
+                .iter()
+                .all(|input| input.script_sig.is_empty())
+        );
+        assert_eq!(cleared.total_size(), tx.total_size() - 0x48);
+    }
+
+    #[test]
+    fn test_estimated_vsize_p2wpkh() {
+        let p2wpkh = script_from_hex("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1");
+        let tx = TransactionBuilder::new()
+            .add_input(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![]),
+                0xFFFFFFFD,
+            )
+            .add_output(50_000, p2wpkh)
+            .build();
+        assert_eq!(tx.base_size(), 82);
+        // witness: item count (1) + 72-byte signature (1 + 72) + 33-byte key (1 + 33)
+        assert_eq!(tx.estimated_vsize(&[108]), Ok(110));
+        assert_eq!(tx.estimated_vsize(&[0]), Ok(82));
+        assert_eq!(tx.estimated_vsize(&[]), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[test]
+    fn test_parse_returns_remaining_bytes() {
+        let first = parse_hex_tx(BLOCK_170_TX_HEX);
+        let second = parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX);
+        let mut buf = first.to_bytes();
+        buf.extend_from_slice(&second.to_bytes());
+
+        let mut parsed = Vec::new();
+        let mut rest = &buf[..];
+        while !rest.is_empty() {
+            let (tx, tail) = BitcoinTransaction::parse(rest).unwrap();
+            parsed.push(tx);
+            rest = tail;
+        }
+        assert_eq!(parsed, vec![first, second]);
+
+        let (cs, rest) = CompactSize::parse(&[0xFD, 0x00, 0x01, 0xAA]).unwrap();
+        assert_eq!((cs.value, rest), (0x100, &[0xAA][..]));
+        let (script, rest) = Script::parse(&[0x01, 0x51]).unwrap();
+        assert_eq!((script.bytes, rest.len()), (vec![0x51], 0));
+    }
+
+    #[test]
+    fn test_op_return_data() {
+        let script = Script::builder()
+            .push_opcode(opcodes::OP_RETURN)
+            .push_slice(b"hello world")
+            .into_script();
+        assert_eq!(script.op_return_data(), Some(&b"hello world"[..]));
+
+        let long = vec![0x42; 80];
+        let script = Script::builder()
+            .push_opcode(opcodes::OP_RETURN)
+            .push_slice(&long)
+            .into_script();
+        assert_eq!(script.op_return_data(), Some(&long[..]));
+
+        let p2pkh = script_from_hex("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac");
+        assert_eq!(p2pkh.op_return_data(), None);
+        // bare OP_RETURN, two pushes, and a truncated push
+        assert_eq!(script_from_hex("6a").op_return_data(), None);
+        assert_eq!(script_from_hex("6a01aa01bb").op_return_data(), None);
+        assert_eq!(script_from_hex("6a05aabb").op_return_data(), None);
+    }
+
+    #[test]
+    fn test_decode_all_length_prefixed() {
+        let txs: Vec<BitcoinTransaction> = [
+            GENESIS_COINBASE_HEX,
+            BLOCK_170_TX_HEX,
+            BIP143_P2WPKH_SIGNED_HEX,
+        ]
+        .iter()
+        .map(|raw| parse_hex_tx(raw))
+        .collect();
+        let mut dump = Vec::new();
+        for tx in &txs {
+            let bytes = tx.to_bytes();
+            dump.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            dump.extend_from_slice(&bytes);
+        }
+        assert_eq!(BitcoinTransaction::decode_all(&dump).unwrap(), txs);
+        assert_eq!(BitcoinTransaction::decode_all(&[]).unwrap(), vec![]);
+
+        // the third member's prefix promises more bytes than remain
+        let third_start = dump.len() - txs[2].total_size();
+        let truncated = &dump[..dump.len() - 10];
+        let err = BitcoinTransaction::decode_all(truncated).unwrap_err();
+        assert_eq!(
+            err,
+            BitcoinError::ParseError {
+                offset: third_start,
+                field: "transaction",
+                kind: Box::new(BitcoinError::InsufficientBytes),
+            }
+        );
+
+        // a member shorter than its contents reports the inner field and absolute offset
+        let mut short_member = dump.clone();
+        let second_prefix = 4 + txs[0].total_size();
+        short_member[second_prefix..second_prefix + 4].copy_from_slice(&200u32.to_le_bytes());
+        let err = BitcoinTransaction::decode_all(&short_member).unwrap_err();
+        assert!(matches!(err, BitcoinError::ParseError { offset, .. } if offset > second_prefix));
+        assert_eq!(err.kind(), &BitcoinError::InsufficientBytes);
+    }
+
+    #[test]
+    fn test_input_mut_bumps_sequence() {
+        let mut tx = parse_hex_tx(BLOCK_170_TX_HEX);
+        let before = tx.to_bytes();
+        tx.input_mut(0).unwrap().sequence = 0xFFFFFFFD;
+        let after = tx.to_bytes();
+
+        let seq_range = tx.input_offsets()[0].end - 4..tx.input_offsets()[0].end;
+        assert_eq!(&after[seq_range.clone()], &[0xFD, 0xFF, 0xFF, 0xFF]);
+        for (i, (a, b)) in before.iter().zip(&after).enumerate() {
+            if !seq_range.contains(&i) {
+                assert_eq!(a, b, "byte {} changed", i);
+            }
+        }
+
+        tx.output_mut(1).unwrap().value -= 1_000;
+        assert_eq!(tx.outputs[1].value, 3_999_999_000);
+        assert!(tx.input_mut(1).is_none());
+        assert!(tx.output_mut(2).is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_compact_size_serde_is_bare_number() {
+        let cs = CompactSize::new(0xFD);
+        let json = serde_json::to_string(&cs).unwrap();
+        assert_eq!(json, "253");
+        assert_eq!(serde_json::from_str::<CompactSize>(&json).unwrap(), cs);
+        assert!(serde_json::from_str::<CompactSize>(r#"{"value":253}"#).is_err());
+    }
+
+    #[test]
+    fn test_is_canonical() {
+        let bytes = hex::decode(BLOCK_170_TX_HEX).unwrap();
+        assert!(BitcoinTransaction::is_canonical(&bytes));
+        assert!(BitcoinTransaction::is_canonical(
+            &hex::decode(BIP143_P2WPKH_SIGNED_HEX).unwrap()
+        ));
+
+        // input count 1 written as 0xFD 0x01 0x00
+        let mut non_minimal = bytes[..4].to_vec();
+        non_minimal.extend_from_slice(&[0xFD, 0x01, 0x00]);
+        non_minimal.extend_from_slice(&bytes[5..]);
+        assert!(!BitcoinTransaction::is_canonical(&non_minimal));
+
+        let mut trailing = bytes.clone();
+        trailing.push(0x00);
+        assert!(!BitcoinTransaction::is_canonical(&trailing));
+    }
+
+    #[test]
+    fn test_script_raw_bytes_and_prefixed_len() {
+        let short = Script::new(vec![0x51, 0x87]);
+        assert_eq!(short.raw_bytes(), &[0x51, 0x87]);
+        assert_eq!(short.prefixed_len(), 3);
+
+        let long = Script::new(vec![0x61; 300]);
+        assert_eq!(long.raw_bytes().len(), 300);
+        assert_eq!(long.prefixed_len(), 303);
+        assert_eq!(long.prefixed_len(), long.to_bytes().len());
+        assert_eq!(Script::new(vec![]).prefixed_len(), 1);
+    }
+
+    #[test]
+    fn test_txid_from_slice() {
+        let hash = parse_hex_tx(BLOCK_170_TX_HEX).txid();
+        assert_eq!(Txid::from_slice(&hash[..]), Ok(Txid(hash)));
+        assert_eq!(
+            Txid::from_slice(&hash[..31]),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            Txid::from_slice(&[0u8; 33]),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_weight_units_and_vsize() {
+        for raw in [
+            GENESIS_COINBASE_HEX,
+            BLOCK_170_TX_HEX,
+            BIP143_P2WPKH_SIGNED_HEX,
+        ] {
+            let tx = parse_hex_tx(raw);
+            let wu = tx.weight_units();
+            assert_eq!(wu, tx.weight());
+            assert_eq!(tx.vsize(), (wu + 3) / WITNESS_SCALE_FACTOR);
+        }
+        let segwit = parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX);
+        assert_eq!((segwit.weight_units(), segwit.vsize()), (1042, 261));
+    }
+
+    #[test]
+    fn test_dust_threshold() {
+        let p2wpkh = script_from_hex("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1");
+        let output = |value| TransactionOutput::new(value, p2wpkh.clone());
+        assert_eq!(
+            output(0).dust_threshold(DUST_RELAY_TX_FEE),
+            P2WPKH_DUST_THRESHOLD
+        );
+        assert!(output(293).is_dust(DUST_RELAY_TX_FEE));
+        assert!(!output(294).is_dust(DUST_RELAY_TX_FEE));
+        assert!(!output(295).is_dust(DUST_RELAY_TX_FEE));
+        // 98 vbytes at 3001 sat/kvB is 294.098 sat, rounded up
+        assert_eq!(output(0).dust_threshold(3_001), 295);
+        assert!(output(294).is_dust(3_001));
+
+        let p2pkh = script_from_hex("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac");
+        assert_eq!(
+            TransactionOutput::new(0, p2pkh).dust_threshold(DUST_RELAY_TX_FEE),
+            546
+        );
+        let op_return = script_from_hex("6a0401020304");
+        assert!(!TransactionOutput::new(0, op_return).is_dust(DUST_RELAY_TX_FEE));
+    }
+
+    #[test]
+    fn test_parse_many_inputs() {
+        let tx = parse_hex_tx(BIP143_P2WPKH_UNSIGNED_HEX);
+        let bytes = tx.to_bytes();
+        let inputs_bytes = &bytes[5..];
+
+        let parsed: Vec<_> = TransactionInput::parse_many(inputs_bytes, 2)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].0, tx.inputs[0]);
+        assert_eq!(parsed[1].0, tx.inputs[1]);
+        assert_eq!(parsed[0].1, 41);
+
+        // asking for more inputs than exist yields one error and stops
+        let results: Vec<_> = TransactionInput::parse_many(&inputs_bytes[..82], 5).collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[2].is_err());
+        assert_eq!(TransactionInput::parse_many(inputs_bytes, 0).count(), 0);
+    }
+
+    #[test]
+    fn test_fee_rate() {
+        let tx = parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX);
+        let input_values = [625_000_000, 600_000_000];
+        assert_eq!(tx.fee(&input_values), Ok(889_210_000));
+        assert_eq!(tx.vsize(), 261);
+        assert_eq!(tx.fee_rate(&input_values), Ok(889_210_000.0 / 261.0));
+
+        let tx = parse_hex_tx(BLOCK_170_TX_HEX);
+        // 50 BTC in, 50 BTC out: no fee
+        assert_eq!(tx.fee_rate(&[5_000_000_000]), Ok(0.0));
+        assert_eq!(tx.fee_rate(&[1]), Err(BitcoinError::InvalidFormat));
+        assert_eq!(tx.fee_rate(&[]), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[test]
+    fn test_segwit_addresses() {
+        // BIP173 and BIP86 examples
+        let p2wpkh = script_from_hex("0014751e76e8199196d454941c45d1b3a323f1433bd6");
+        assert_eq!(
+            p2wpkh.address(Network::Bitcoin).unwrap(),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+        );
+        assert_eq!(
+            p2wpkh.address(Network::Testnet).unwrap(),
+            "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"
+        );
+        assert!(
+            p2wpkh
+                .address(Network::Regtest)
+                .unwrap()
+                .starts_with("bcrt1q")
+        );
+
+        let p2tr =
+            script_from_hex("5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c");
+        assert_eq!(
+            p2tr.address(Network::Bitcoin).unwrap(),
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+        );
+
+        // not a witness program, and a v0 program of invalid length
+        assert_eq!(
+            script_from_hex("6a0401020304").address(Network::Bitcoin),
+            None
+        );
+        assert_eq!(
+            script_from_hex("0003aabbcc").address(Network::Bitcoin),
+            None
+        );
+    }
+
+    #[test]
+    fn test_base58_addresses() {
+        let p2pkh = script_from_hex("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac");
+        assert_eq!(
+            p2pkh.address(Network::Bitcoin).unwrap(),
+            "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"
+        );
+        assert_eq!(
+            p2pkh.address(Network::Testnet).unwrap(),
+            "mpXwg4jMtRhuSpVq4xS3HFHmCmWp9NyGKt"
+        );
+
+        let p2sh = script_from_hex("a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87");
+        assert_eq!(
+            p2sh.address(Network::Bitcoin).unwrap(),
+            "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy"
+        );
+        assert!(p2sh.address(Network::Testnet).unwrap().starts_with('2'));
+
+        // bare P2PK has no address
+        let p2pk = script_from_hex(
+            "410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac",
+        );
+        assert_eq!(p2pk.address(Network::Bitcoin), None);
+    }
+
+    #[test]
+    fn test_encode_into_reused_buffer() {
+        let tx = BitcoinTransaction::from_hex(BIP143_P2WPKH_UNSIGNED_HEX).unwrap();
+        let mut buf = Vec::new();
+        assert_eq!(tx.encode_into(&mut buf), tx.to_bytes().len());
+        let first = buf.clone();
+        buf.clear();
+        tx.encode_into(&mut buf);
+        assert_eq!(buf, first);
+        assert_eq!(buf, tx.to_bytes());
+
+        // Components append after existing contents
+        let mut buf = vec![0xaa];
+        tx.outputs[0].encode_into(&mut buf);
+        assert_eq!(buf[0], 0xaa);
+        assert_eq!(&buf[1..], tx.outputs[0].to_bytes());
+    }
+
+    #[test]
+    fn test_from_hex_ignores_whitespace() {
+        let hex = BIP143_P2WPKH_UNSIGNED_HEX;
+        let spaced = format!(
+            "  {} \n{}\r\n\t{}\n",
+            &hex[..10],
+            &hex[10..100],
+            &hex[100..]
+        );
+        assert_eq!(
+            BitcoinTransaction::from_hex(&spaced).unwrap(),
+            BitcoinTransaction::from_hex(hex).unwrap()
+        );
+
+        let bad = format!("{}zz", &hex[..hex.len() - 2]);
+        assert_eq!(
+            BitcoinTransaction::from_hex(&bad),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_conflicts_with_and_signals_rbf() {
+        let spend = |outpoints: &[(u8, u32)], sequence| {
+            let inputs = outpoints
+                .iter()
+                .map(|&(txid, vout)| {
+                    TransactionInput::new(
+                        OutPoint::new(dummy_txid(txid), vout),
+                        Script::new(vec![]),
+                        sequence,
+                    )
+                })
+                .collect();
+            let outputs = vec![TransactionOutput::new(1_000, Script::new(vec![0x51]))];
+            BitcoinTransaction::new(2, inputs, outputs, 0)
+        };
+
+        let original = spend(&[(1, 0), (2, 1)], 0xFFFFFFFD);
+        let replacement = spend(&[(2, 1)], 0xFFFFFFFF);
+        let unrelated = spend(&[(1, 1), (3, 0)], 0xFFFFFFFE);
+
+        assert!(original.conflicts_with(&replacement));
+        assert!(replacement.conflicts_with(&original));
+        assert!(!original.conflicts_with(&unrelated));
+
+        assert!(original.signals_rbf());
+        assert!(!replacement.signals_rbf());
+        assert!(!unrelated.signals_rbf());
+    }
+
+    #[test]
+    fn test_script_equals_semantically() {
+        // <3 bytes> OP_DROP, pushed directly and via OP_PUSHDATA1
+        let direct = Script::new(vec![0x03, 0xaa, 0xbb, 0xcc, 0x75]);
+        let pushdata1 = Script::new(vec![0x4c, 0x03, 0xaa, 0xbb, 0xcc, 0x75]);
+        assert_ne!(direct, pushdata1);
+        assert!(direct.equals_semantically(&pushdata1));
+        assert!(pushdata1.equals_semantically(&direct));
+
+        let different = Script::new(vec![0x4c, 0x03, 0xaa, 0xbb, 0xcd, 0x75]);
+        assert!(!direct.equals_semantically(&different));
+        let longer = Script::new(vec![0x03, 0xaa, 0xbb, 0xcc, 0x75, 0x51]);
+        assert!(!direct.equals_semantically(&longer));
+
+        // Truncated pushes fall back to exact comparison
+        let truncated = Script::new(vec![0x4c, 0x05, 0xaa]);
+        assert!(truncated.equals_semantically(&truncated.clone()));
+        assert!(!truncated.equals_semantically(&Script::new(vec![0x05, 0xaa])));
+    }
+
+    #[test]
+    fn test_is_standard_size() {
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            Script::new(vec![]),
+            0xFFFFFFFF,
+        );
+        let output = TransactionOutput::new(1_000, Script::new(vec![0x6a; 9_000]));
+        let small = BitcoinTransaction::new(2, vec![input.clone()], vec![output.clone()], 0);
+        assert!(small.is_standard_size());
+
+        // 12 outputs of ~9kB each is over 100kvB
+        let large = BitcoinTransaction::new(2, vec![input], vec![output; 12], 0);
+        assert!(large.weight_units() > MAX_STANDARD_TX_WEIGHT);
+        assert!(!large.is_standard_size());
+    }
+
+    #[test]
+    fn test_compact_size_decode_u8_fast() {
+        for byte in 0..=0xFCu8 {
+            assert_eq!(
+                CompactSize::decode_u8_fast(byte),
+                Some(CompactSize::from_bytes(&[byte]).unwrap())
+            );
+        }
+        for prefix in [0xFD, 0xFE, 0xFF] {
+            assert_eq!(CompactSize::decode_u8_fast(prefix), None);
+        }
+    }
+
+    #[test]
+    fn test_version_signed_roundtrip() {
+        let inputs = vec![TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            Script::new(vec![]),
+            0xFFFFFFFF,
+        )];
+        let outputs = vec![TransactionOutput::new(1_000, Script::new(vec![0x51]))];
+        let tx = BitcoinTransaction::new(0xFFFF_FFFF, inputs, outputs, 0);
+        let bytes = tx.to_bytes();
+        assert_eq!(&bytes[..4], &[0xFF; 4]);
+
+        let (parsed, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.version, 0xFFFF_FFFF);
+        assert_eq!(parsed.version_signed(), -1);
+        assert_eq!(parsed.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_coinbase_constructor() {
+        let payout = Script::new(vec![
+            0x00, 0x14, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc,
+            0xdd, 0xee, 0xff, 0x00, 0x11, 0x22, 0x33, 0x44,
+        ]);
+        for height in [0, 1, 16, 17, 128, 255, 840_000, 8_388_608] {
+            let tx = BitcoinTransaction::coinbase(height, payout.clone(), 312_500_000);
+            assert!(tx.is_coinbase());
+            assert_eq!(tx.inputs[0].coinbase_height(), Some(height));
+            assert_eq!(tx.inputs[0].sequence, 0xFFFFFFFF);
+            assert_eq!(
+                tx.outputs,
+                vec![TransactionOutput::new(312_500_000, payout.clone())]
+            );
+        }
+
+        // Height 840000 is pushed as 3 little-endian bytes
+        let tx = BitcoinTransaction::coinbase(840_000, payout, 0);
+        assert_eq!(
+            tx.inputs[0].script_sig.to_vec(),
+            vec![0x03, 0x40, 0xd1, 0x0c, 0x00]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_script_json_is_hex() {
+        let script = Script::new(vec![0x76, 0xa9, 0x14, 0x88, 0xac]);
+        let json = serde_json::to_string(&script).unwrap();
+        assert_eq!(json, "\"76a91488ac\"");
+        assert_eq!(serde_json::from_str::<Script>(&json).unwrap(), script);
+
+        let output = TransactionOutput::new(1_000, script);
+        let value = serde_json::to_value(&output).unwrap();
+        assert_eq!(value["script_pubkey"], "76a91488ac");
+
+        assert!(serde_json::from_str::<Script>("\"76a9zz\"").is_err());
+        assert!(serde_json::from_str::<Script>("\"76a\"").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_witness_json_is_hex() {
+        let input = TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0)
+            .with_witness(vec![vec![0x30, 0x44], vec![]]);
+        let value = serde_json::to_value(&input).unwrap();
+        assert_eq!(value["witness"], serde_json::json!(["3044", ""]));
+        assert_eq!(
+            serde_json::from_value::<TransactionInput>(value.clone()).unwrap(),
+            input
+        );
+
+        // A missing witness is an empty one
+        let mut legacy = value;
+        legacy.as_object_mut().unwrap().remove("witness");
+        let parsed = serde_json::from_value::<TransactionInput>(legacy).unwrap();
+        assert!(parsed.witness.is_empty());
+
+        let mut bad = serde_json::to_value(&input).unwrap();
+        bad["witness"] = serde_json::json!(["30zz"]);
+        assert!(serde_json::from_value::<TransactionInput>(bad).is_err());
+    }
+
+    #[test]
+    fn test_sigop_count() {
+        // 2-of-3 bare multisig
+        let mut multisig = Script::builder().push_int(2);
+        for key in 1..=3u8 {
+            multisig = multisig.push_slice(&[key; 33]);
+        }
+        let multisig = multisig
+            .push_int(3)
+            .push_opcode(opcodes::OP_CHECKMULTISIG)
+            .into_script();
+        assert_eq!(multisig.sigop_count(false), 20);
+        assert_eq!(multisig.sigop_count(true), 3);
+
+        let p2pkh =
+            Script::new(hex::decode("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac").unwrap());
+        assert_eq!(p2pkh.sigop_count(true), 1);
+
+        let inputs = vec![TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            Script::new(vec![opcodes::OP_CHECKSIGVERIFY]),
+            0xFFFFFFFF,
+        )];
+        let outputs = vec![
+            TransactionOutput::new(1_000, multisig),
+            TransactionOutput::new(1_000, p2pkh),
+        ];
+        let tx = BitcoinTransaction::new(2, inputs, outputs, 0);
+        assert_eq!(tx.legacy_sigops(), 22);
+    }
+
+    #[test]
+    fn test_from_bytes_partial() {
+        let inputs = vec![
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![0x51]),
+                0xFFFFFFFF,
+            ),
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(2), 1),
+                Script::new(vec![0x52]),
+                0xFFFFFFFF,
+            ),
+        ];
+        let outputs = vec![TransactionOutput::new(1_000, Script::new(vec![0x51]))];
+        let tx = BitcoinTransaction::new(2, inputs.clone(), outputs, 0);
+        let bytes = tx.to_bytes();
+
+        assert_eq!(
+            BitcoinTransaction::from_bytes_partial(&bytes),
+            (Some(tx), bytes.len(), None)
+        );
+
+        // version + count + first input, then all but the last byte of the second
+        let first_input_end = 4 + 1 + inputs[0].to_bytes().len();
+        let (partial, consumed, error) =
+            BitcoinTransaction::from_bytes_partial(&bytes[..first_input_end + 41]);
+        let partial = partial.unwrap();
+        assert_eq!(partial.version, 2);
+        assert_eq!(partial.inputs, vec![inputs[0].clone()]);
+        assert!(partial.outputs.is_empty());
+        assert_eq!(consumed, first_input_end);
+        assert_eq!(error.unwrap().kind(), &BitcoinError::InsufficientBytes);
+
+        let (partial, consumed, error) = BitcoinTransaction::from_bytes_partial(&bytes[..3]);
+        assert_eq!((partial, consumed), (None, 0));
+        assert_eq!(error.unwrap().kind(), &BitcoinError::InsufficientBytes);
+    }
+
+    #[test]
+    fn test_sighash_type_flags() {
+        let cases = [
+            (0x01, SighashType::All),
+            (0x02, SighashType::None),
+            (0x03, SighashType::Single),
+            (0x81, SighashType::AllPlusAnyoneCanPay),
+            (0x82, SighashType::NonePlusAnyoneCanPay),
+            (0x83, SighashType::SinglePlusAnyoneCanPay),
+        ];
+        for (value, sighash_type) in cases {
+            assert_eq!(SighashType::from_u32(value), Ok(sighash_type));
+            assert_eq!(sighash_type.to_u32(), value);
+        }
+        for invalid in [0x00, 0x04, 0x80, 0x41, 0x0101] {
+            assert_eq!(
+                SighashType::from_u32(invalid),
+                Err(BitcoinError::InvalidFormat)
+            );
+        }
+    }
+
+    #[test]
+    fn test_same_txid_ignores_witness() {
+        let segwit = parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX);
+        let mut stripped = segwit.clone();
+        for input in &mut stripped.inputs {
+            input.witness.clear();
+        }
+        assert_ne!(segwit, stripped);
+        assert!(segwit.same_txid(&stripped));
+        assert!(stripped.same_txid(&segwit));
+
+        let mut changed = stripped.clone();
+        changed.lock_time += 1;
+        assert!(!segwit.same_txid(&changed));
+    }
+
+    #[test]
+    fn test_compact_size_count_and_checked_add() {
+        let inputs = [0u8; 3];
+        let outputs = [0u8; 250];
+        let total = CompactSize::count(&inputs)
+            .checked_add(CompactSize::count(&outputs).value)
+            .unwrap();
+        assert_eq!(total, CompactSize::new(253));
+        assert_eq!(total.encoded_len(), 3);
+
+        assert_eq!(
+            CompactSize::new(u64::MAX - 1).checked_add(1),
+            Some(CompactSize::new(u64::MAX))
+        );
+        assert_eq!(CompactSize::new(u64::MAX).checked_add(1), None);
+    }
+
+    #[test]
+    fn test_txid_be_bytes() {
+        // Genesis coinbase, as shown by explorers
+        let explorer = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+        let txid = Txid(parse_hex_tx(GENESIS_COINBASE_HEX).txid());
+        assert_eq!(hex::encode(txid.to_be_bytes()), explorer);
+        assert_eq!(txid.0[0], 0x3b);
+
+        let be: [u8; 32] = hex::decode(explorer).unwrap().try_into().unwrap();
+        assert_eq!(Txid::from_be_bytes(be), txid);
+        assert_eq!(Txid::from_hex(explorer).unwrap(), txid);
+    }
+
+    #[test]
+    fn test_strip_witness() {
+        let segwit = parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX);
+        let stripped = segwit.strip_witness();
+        assert!(!stripped.has_witness());
+        assert_eq!(stripped.to_bytes(), segwit.to_bytes_without_witness());
+
+        // Same as building the transaction without witnesses in the first place
+        let legacy = BitcoinTransaction::new(
+            segwit.version,
+            segwit
+                .inputs
+                .iter()
+                .map(|input| {
+                    TransactionInput::new(
+                        input.previous_output.clone(),
+                        input.script_sig.clone(),
+                        input.sequence,
+                    )
+                })
+                .collect(),
+            segwit.outputs.clone(),
+            segwit.lock_time,
+        );
+        assert_eq!(stripped.to_bytes(), legacy.to_bytes());
+        assert_eq!(segwit.txid(), legacy.txid());
+    }
+
+    #[test]
+    fn test_parser_length_overflow_is_an_error() {
+        // A witness item claiming u32::MAX bytes: offset + length overflows
+        // usize on 32-bit targets, which must fail rather than wrap or panic
+        let tx = parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX);
+        let mut bytes = tx.to_bytes_without_witness();
+        let lock_time = bytes.split_off(bytes.len() - 4);
+        bytes.splice(4..4, [0x00, 0x01]);
+        bytes.extend_from_slice(&[0x01, 0xFE]);
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(&lock_time);
+        let err = BitcoinTransaction::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InsufficientBytes);
+        assert!(matches!(
+            err,
+            BitcoinError::ParseError {
+                field: "witness",
+                ..
+            }
+        ));
+
+        // A dump whose length prefix runs to u32::MAX
+        let mut dump = u32::MAX.to_le_bytes().to_vec();
+        dump.extend_from_slice(&tx.to_bytes());
+        assert_eq!(
+            BitcoinTransaction::decode_all(&dump).unwrap_err().kind(),
+            &BitcoinError::InsufficientBytes
+        );
+    }
+
+    #[test]
+    fn test_script_classify() {
+        let key = [0x02; 33];
+        let multisig = Script::builder()
+            .push_int(1)
+            .push_slice(&key)
+            .push_slice(&[0x04; 65])
+            .push_int(2)
+            .push_opcode(opcodes::OP_CHECKMULTISIG)
+            .into_script();
+        let cases = [
+            (
+                Script::builder()
+                    .push_slice(&key)
+                    .push_opcode(opcodes::OP_CHECKSIG)
+                    .into_script(),
+                ScriptType::P2pk,
+            ),
+            (
+                script_from_hex("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac"),
+                ScriptType::P2pkh,
+            ),
+            (
+                script_from_hex("a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87"),
+                ScriptType::P2sh,
+            ),
+            (
+                script_from_hex("0014751e76e8199196d454941c45d1b3a323f1433bd6"),
+                ScriptType::P2wpkh,
+            ),
+            (
+                Script::builder()
+                    .push_opcode(opcodes::OP_0)
+                    .push_slice(&[0xaa; 32])
+                    .into_script(),
+                ScriptType::P2wsh,
+            ),
+            (
+                Script::builder()
+                    .push_opcode(opcodes::OP_1)
+                    .push_slice(&[0xaa; 32])
+                    .into_script(),
+                ScriptType::P2tr,
+            ),
+            (script_from_hex("6a0568656c6c6f"), ScriptType::OpReturn),
+            (multisig.clone(), ScriptType::Multisig),
+            (Script::new(vec![0x51]), ScriptType::NonStandard),
+            (Script::new(vec![]), ScriptType::NonStandard),
+        ];
+        for (script, expected) in cases {
+            assert_eq!(script.classify(), expected, "{}", script);
+        }
+
+        // Declared key count must match the pushed keys
+        let mut wrong_n = multisig.to_vec();
+        let n = wrong_n.len() - 2;
+        wrong_n[n] = opcodes::OP_16;
+        assert_eq!(Script::new(wrong_n).classify(), ScriptType::NonStandard);
+        assert!(script_from_hex("0014751e76e8199196d454941c45d1b3a323f1433bd6").is_p2wpkh());
+    }
+
+    #[test]
+    fn test_hashes_known_vectors() {
+        assert_eq!(
+            hex::encode(hashes::sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex::encode(hashes::sha256d(b"")),
+            "5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456"
+        );
+        let tx = parse_hex_tx(GENESIS_COINBASE_HEX);
+        assert_eq!(hashes::sha256d(&tx.to_bytes()), tx.txid());
+    }
+
+    #[test]
+    fn test_taproot_output_key() {
+        // BIP86 test vector, first receiving address of account 0
+        let script =
+            script_from_hex("5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c");
+        assert_eq!(script.classify(), ScriptType::P2tr);
+        assert_eq!(
+            hex::encode(script.taproot_output_key().unwrap()),
+            "a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c"
+        );
+        assert_eq!(
+            script.address(Network::Bitcoin).unwrap(),
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+        );
+
+        // v0 programs of the same length are not taproot
+        let p2wsh =
+            script_from_hex("0020a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c");
+        assert_eq!(p2wsh.taproot_output_key(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_write_all_batch() {
+        let txs = vec![
+            parse_hex_tx(GENESIS_COINBASE_HEX),
+            parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX),
+            parse_hex_tx(BLOCK_170_TX_HEX),
+        ];
+        let mut out = Vec::new();
+        let written = BitcoinTransaction::write_all(&txs, &mut out).unwrap();
+        let expected: Vec<u8> = txs.iter().flat_map(|tx| tx.to_bytes()).collect();
+        assert_eq!(out, expected);
+        assert_eq!(written, expected.len());
+    }
+
+    #[test]
+    fn test_outpoint_key_roundtrip() {
+        let outpoint = OutPoint::new(dummy_txid(0xab), 42);
+        let key = outpoint.to_key();
+        assert_eq!(key, outpoint.to_string());
+        assert!(key.ends_with(":42"));
+        assert_eq!(OutPoint::from_key(&key), Ok(outpoint));
+
+        let txid = &key[..64];
+        for non_canonical in [
+            format!("{}:042", txid),
+            format!("{}:+42", txid),
+            format!("{}:42", txid.to_uppercase()),
+        ] {
+            assert!(non_canonical.parse::<OutPoint>().is_ok());
+            assert_eq!(
+                OutPoint::from_key(&non_canonical),
+                Err(BitcoinError::InvalidFormat)
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_bytes_with_limits() {
+        let tx = parse_hex_tx(BIP143_P2WPKH_UNSIGNED_HEX);
+        let bytes = tx.to_bytes();
+        let limits = ParseLimits {
+            max_inputs: 2,
+            max_outputs: 2,
+            max_script_len: 25,
+        };
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with_limits(&bytes, &limits),
+            Ok((tx.clone(), bytes.len()))
+        );
+
+        let too_strict = [
+            (
+                ParseLimits {
+                    max_inputs: 1,
+                    ..limits
+                },
+                "input count",
+            ),
+            (
+                ParseLimits {
+                    max_outputs: 1,
+                    ..limits
+                },
+                "output count",
+            ),
+            (
+                ParseLimits {
+                    max_script_len: 24,
+                    ..limits
+                },
+                "output",
+            ),
+        ];
+        for (limits, expected_field) in too_strict {
+            let err = BitcoinTransaction::from_bytes_with_limits(&bytes, &limits).unwrap_err();
+            assert_eq!(err.kind(), &BitcoinError::InvalidFormat);
+            assert!(
+                matches!(err, BitcoinError::ParseError { field, .. } if field == expected_field),
+                "{err}"
+            );
+        }
+
+        // Long scriptSigs are caught on the input side
+        let signed = parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX).to_bytes();
+        let err = BitcoinTransaction::from_bytes_with_limits(&signed, &limits).unwrap_err();
+        assert!(matches!(
+            err,
+            BitcoinError::ParseError { field: "input", .. }
+        ));
+
+        assert_eq!(ParseLimits::standard().max_script_len, 1_650);
+        assert!(ParseLimits::consensus().max_inputs > ParseLimits::standard().max_inputs);
+        assert!(
+            BitcoinTransaction::from_bytes_with_limits(&bytes, &ParseLimits::standard()).is_ok()
+        );
+
+        // Outputs past MAX_SCRIPT_SIZE are consensus-valid and only rejected when asked
+        let mut big = tx.clone();
+        big.outputs[0].script_pubkey = Script::new(vec![opcodes::OP_RETURN; MAX_SCRIPT_SIZE + 1]);
+        let big_bytes = big.to_bytes();
+        assert!(
+            BitcoinTransaction::from_bytes_with_limits(&big_bytes, &ParseLimits::consensus())
+                .is_ok()
+        );
+        let capped = ParseLimits {
+            max_script_len: MAX_SCRIPT_SIZE,
+            ..ParseLimits::consensus()
+        };
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with_limits(&big_bytes, &capped)
+                .unwrap_err()
+                .kind(),
+            &BitcoinError::InvalidFormat
+        );
+    }
+
+    #[test]
+    fn test_fee_rate_bucket() {
+        let tx = parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX);
+        let input_values = [625_000_000, 600_000_000];
+        // 889_210_000 sat / 261 vB = 3_406_934.8 sat/vB
+        assert_eq!(tx.fee_rate_bucket(&input_values, 1.0), Ok(3_406_934));
+        assert_eq!(tx.fee_rate_bucket(&input_values, 1_000_000.0), Ok(3));
+        assert_eq!(
+            tx.fee_rate_bucket(&input_values, 0.0),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            tx.fee_rate_bucket(&input_values, f64::NAN),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            tx.fee_rate_bucket(&[1], 1.0),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_multisig_info() {
+        // 2-of-3 redeem script
+        let keys = [
+            hex::decode("02b4632d08485ff1df2db55b9dafd23347d1c47a457072a1e87be26896549a8737")
+                .unwrap(),
+            hex::decode("03774ae7f858a9411e5ef4246b70c65aac5649980be5c17891bbec17895da008cb")
+                .unwrap(),
+            hex::decode("02e2c34f7b8ed3a6ff0a6e5a6ba8f4c6e1ae0d58d3c8f3fbd9e0f3e1d4a53d1bcd")
+                .unwrap(),
+        ];
+        let mut builder = Script::builder().push_int(2);
+        for key in &keys {
+            builder = builder.push_slice(key);
+        }
+        let script = builder
+            .push_int(3)
+            .push_opcode(opcodes::OP_CHECKMULTISIG)
+            .into_script();
+        assert_eq!(script.multisig_info(), Some((2, keys.to_vec())));
+
+        // n disagrees with the number of keys
+        let mut bytes = script.to_vec();
+        let n = bytes.len() - 2;
+        bytes[n] = opcodes::OP_1 + 1;
+        assert_eq!(Script::new(bytes).multisig_info(), None);
+        // m > n
+        let mut bytes = script.to_vec();
+        bytes[0] = opcodes::OP_1 + 3;
+        assert_eq!(Script::new(bytes).multisig_info(), None);
+        assert_eq!(
+            script_from_hex("a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87").multisig_info(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_sort_bip69() {
+        // Internal order puts `low_internal` first, display order the reverse
+        let mut low_internal = [0u8; 32];
+        low_internal[31] = 0xff;
+        let mut high_internal = [0u8; 32];
+        high_internal[0] = 0x01;
+        let input = |txid, vout| {
+            TransactionInput::new(OutPoint::new(txid, vout), Script::new(vec![]), 0xFFFFFFFF)
+        };
+        let output =
+            |value, script: &[u8]| TransactionOutput::new(value, Script::new(script.to_vec()));
+
+        let mut tx = BitcoinTransaction::new(
+            2,
+            vec![
+                input(low_internal, 0),
+                input(high_internal, 1),
+                input(high_internal, 0),
+            ],
+            vec![
+                output(2_000, &[0x51]),
+                output(1_000, &[0x52]),
+                output(1_000, &[0x51, 0x00]),
+            ],
+            0,
+        );
+        tx.inputs[0].witness = vec![vec![0xaa]];
+        tx.sort_bip69();
+
+        assert_eq!(
+            tx.prevouts(),
+            vec![
+                &OutPoint::new(high_internal, 0),
+                &OutPoint::new(high_internal, 1),
+                &OutPoint::new(low_internal, 0),
+            ]
+        );
+        assert_eq!(tx.inputs[2].witness, vec![vec![0xaa]]);
+        assert_eq!(
+            tx.outputs,
+            vec![
+                output(1_000, &[0x51, 0x00]),
+                output(1_000, &[0x52]),
+                output(2_000, &[0x51])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_final_at() {
+        let inputs = vec![TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            Script::new(vec![]),
+            0xFFFFFFFE,
+        )];
+        let outputs = vec![TransactionOutput::new(1_000, Script::new(vec![0x51]))];
+        let at = |lock_time| BitcoinTransaction::new(2, inputs.clone(), outputs.clone(), lock_time);
+
+        // Height-locked
+        let tx = at(800_000);
+        assert!(!tx.is_final_at(800_000, u32::MAX));
+        assert!(tx.is_final_at(800_001, 0));
+
+        // Time-locked, against median-time-past
+        let tx = at(1_700_000_000);
+        assert!(!tx.is_final_at(u32::MAX, 1_700_000_000));
+        assert!(tx.is_final_at(0, 1_700_000_001));
+
+        // Disabled: zero lock time, or every input final
+        assert!(at(0).is_final_at(0, 0));
+        let mut tx = at(800_000);
+        tx.inputs[0].sequence = 0xFFFFFFFF;
+        assert!(tx.is_final_at(0, 0));
+    }
+
+    #[test]
+    fn test_serialize_with_txid() {
+        for hex in [BIP143_P2WPKH_SIGNED_HEX, GENESIS_COINBASE_HEX] {
+            let tx = parse_hex_tx(hex);
+            let (bytes, txid) = tx.serialize_with_txid();
+            assert_eq!(bytes, tx.to_bytes());
+            assert_eq!(txid, tx.txid());
+        }
+    }
+
+    #[test]
+    fn test_psbt_roundtrip() {
+        let tx = parse_hex_tx(BIP143_P2WPKH_UNSIGNED_HEX);
+        let tx_bytes = tx.to_bytes();
+        // magic, global tx + one proprietary pair, then one map per input
+        // (the second carrying a sighash type) and per output
+        let mut bytes = b"psbt\xff".to_vec();
+        bytes.push(0x01);
+        bytes.push(0x00);
+        bytes.extend_from_slice(&CompactSize::new(tx_bytes.len() as u64).to_bytes());
+        bytes.extend_from_slice(&tx_bytes);
+        bytes.extend_from_slice(&[0x02, 0xfc, 0x01, 0x01, 0xaa, 0x00]);
+        bytes.push(0x00);
+        bytes.extend_from_slice(&[0x01, 0x03, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00]);
+        bytes.extend_from_slice(&[0x00, 0x00]);
+
+        let (psbt, consumed) = Psbt::from_bytes(&bytes).unwrap();
+        assert_eq!(consumed, bytes.len());
+        assert_eq!(psbt.unsigned_tx, tx);
+        assert_eq!(psbt.global, vec![(vec![0xfc, 0x01], vec![0xaa])]);
+        assert_eq!(
+            psbt.inputs,
+            vec![vec![], vec![(vec![0x03], vec![0x01, 0x00, 0x00, 0x00])]]
+        );
+        assert_eq!(psbt.outputs, vec![vec![], vec![]]);
+        assert_eq!(psbt.to_bytes(), bytes);
+
+        // Bad magic, missing maps, and a signed transaction are rejected
+        let mut bad_magic = bytes.clone();
+        bad_magic[4] = 0x00;
+        assert_eq!(
+            Psbt::from_bytes(&bad_magic).unwrap_err().kind(),
+            &BitcoinError::InvalidFormat
+        );
+        assert_eq!(
+            Psbt::from_bytes(&bytes[..bytes.len() - 1])
+                .unwrap_err()
+                .kind(),
+            &BitcoinError::InsufficientBytes
+        );
+        let signed = Psbt {
+            unsigned_tx: parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX),
+            ..psbt
+        };
+        assert_eq!(
+            Psbt::from_bytes(&signed.to_bytes()).unwrap_err().kind(),
+            &BitcoinError::InvalidFormat
+        );
+    }
+
+    #[test]
+    fn test_set_all_sequences() {
+        let mut tx = parse_hex_tx(BIP143_P2WPKH_UNSIGNED_HEX);
+        assert!(tx.signals_rbf());
+        tx.set_all_sequences(0xFFFFFFFF);
+        assert!(tx.inputs.iter().all(|input| input.sequence == 0xFFFFFFFF));
+        assert!(!tx.signals_rbf());
+
+        let (reparsed, _) = BitcoinTransaction::from_bytes(&tx.to_bytes()).unwrap();
+        assert_eq!(reparsed, tx);
+        let ranges = tx.input_offsets();
+        for range in ranges {
+            assert_eq!(&tx.to_bytes()[range.end - 4..range.end], &[0xFF; 4]);
+        }
+    }
+
+    #[test]
+    fn test_compact_size_as_usize_checked() {
+        assert_eq!(CompactSize::new(0).as_usize_checked(), Ok(0));
+        assert_eq!(
+            CompactSize::new(u32::MAX as u64).as_usize_checked(),
+            Ok(u32::MAX as usize)
+        );
+        assert_eq!(
+            CompactSize::new(u32::MAX as u64 + 1).as_usize_checked(),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        // A witness item declaring a u64 length is rejected before slicing
+        let tx = parse_hex_tx(BIP143_P2WPKH_SIGNED_HEX);
+        let mut bytes = tx.to_bytes_without_witness();
+        let lock_time = bytes.split_off(bytes.len() - 4);
+        bytes.splice(4..4, [0x00, 0x01]);
+        bytes.extend_from_slice(&[0x01, 0xFF]);
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+        bytes.extend_from_slice(&lock_time);
+        let err = BitcoinTransaction::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.kind(), &BitcoinError::InvalidFormat);
+        assert!(matches!(
+            err,
+            BitcoinError::ParseError {
+                field: "witness",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_fee_rejects_output_overflow() {
+        let inputs = vec![
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![]),
+                0xFFFFFFFF,
+            ),
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(2), 0),
+                Script::new(vec![]),
+                0xFFFFFFFF,
+            ),
+        ];
+        let outputs = vec![
+            TransactionOutput::new(u64::MAX, Script::new(vec![0x51])),
+            TransactionOutput::new(1, Script::new(vec![0x51])),
+        ];
+        let tx = BitcoinTransaction::new(2, inputs, outputs, 0);
+        let input_values = [u64::MAX - 1, 1];
+        assert_eq!(tx.fee(&input_values), Err(BitcoinError::InvalidFormat));
+        assert_eq!(tx.fee_rate(&input_values), Err(BitcoinError::InvalidFormat));
     }
 }